@@ -0,0 +1,225 @@
+// Animated PNG, a lossless alternative to GIF for the recorder.
+//
+// The `png` crate does not write APNG, so each frame is encoded as a PNG by
+// the crate and its compressed data (IDAT) is moved into the animation chunks:
+//
+//     signature, IHDR, acTL, fcTL, IDAT (the first frame),
+//     fcTL, fdAT (the second frame), fcTL, fdAT, ..., IEND
+//
+// The frames after the first one cover only the rectangle that changed from
+// the previous frame and replace the pixels there (`APNG_BLEND_OP_SOURCE`).
+// The frames are kept compressed until `finish` because acTL, the number of
+// frames, comes first.
+
+use crate::render::Image;
+
+struct Frame {
+    left:   u32,
+    top:    u32,
+    width:  u32,
+    height: u32,
+    delay:  u16,     // in 1/100 seconds
+    data:   Vec<u8>, // zlib stream of the frame
+}
+
+pub struct ApngEncoder {
+    loops:    u32, // 0 means forever
+    frames:   Vec<Frame>,
+    previous: Option<Image>,
+}
+
+// encodes an RGBA image as a PNG
+pub fn encode_png(image: &Image) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut buffer, image.width, image.height);
+        encoder.set_color(png::ColorType::RGBA);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&image.pixels).unwrap();
+    }
+    buffer
+}
+
+// decodes a PNG written by `encode_png`
+pub fn decode_png(png: &[u8]) -> Image {
+    let (info, mut reader) = png::Decoder::new(png).read_info().unwrap();
+    let mut pixels = vec![0; info.buffer_size()];
+    reader.next_frame(&mut pixels).unwrap();
+    Image{width: info.width, height: info.height, pixels}
+}
+
+// the concatenated data of the IDAT chunks of a PNG
+fn image_data(png: &[u8]) -> Vec<u8> {
+    let mut data = Vec::new();
+    let mut pos = 8; // signature
+    while pos + 8 <= png.len() {
+        let len = u32::from_be_bytes([png[pos], png[pos + 1], png[pos + 2], png[pos + 3]]) as usize;
+        if &png[pos + 4..pos + 8] == b"IDAT" {
+            data.extend_from_slice(&png[pos + 8..pos + 8 + len]);
+        }
+        pos += 12 + len; // length, type, data and CRC
+    }
+    data
+}
+
+impl ApngEncoder {
+    pub fn new(loops: u32) -> Self {
+        ApngEncoder{loops, frames: Vec::new(), previous: None}
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    // adds a frame. It fails if the size differs from the first frame, which
+    // is the size of the image.
+    pub fn add_frame(&mut self, image: &Image, delay: u16) -> Result<(), String> {
+        let (left, top, width, height) = match &self.previous {
+            Some(previous) if (previous.width, previous.height) != (image.width, image.height) =>
+                return Err(format!("the frame is {}x{}, but the animation is {}x{}",
+                                   image.width, image.height, previous.width, previous.height)),
+            Some(previous) => changed_rect(previous, image),
+            None => (0, 0, image.width, image.height),
+        };
+        let mut part = Image::new(width, height, [0; 4]);
+        for y in 0..height {
+            let src = (((top + y) * image.width + left) * 4) as usize;
+            let dst = (y * width * 4) as usize;
+            part.pixels[dst..dst + (width * 4) as usize]
+                .copy_from_slice(&image.pixels[src..src + (width * 4) as usize]);
+        }
+        let data = image_data(&encode_png(&part));
+        self.frames.push(Frame{left, top, width, height, delay, data});
+        self.previous = Some(image.clone());
+        Ok(())
+    }
+
+    // changes the delay of the last frame, e.g. to hold the final position
+    pub fn set_last_delay(&mut self, delay: u16) {
+        if let Some(frame) = self.frames.last_mut() {
+            frame.delay = delay;
+        }
+    }
+
+    // writes the APNG and clears the frames
+    pub fn finish(&mut self) -> Vec<u8> {
+        let frames = std::mem::take(&mut self.frames);
+        let previous = self.previous.take();
+        let (width, height) = match &previous {
+            Some(image) => (image.width, image.height),
+            None => return Vec::new(),
+        };
+
+        let mut buffer = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut buffer, width, height);
+            encoder.set_color(png::ColorType::RGBA);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header().unwrap();
+
+            let mut actl = Vec::new();
+            actl.extend_from_slice(&(frames.len() as u32).to_be_bytes());
+            actl.extend_from_slice(&self.loops.to_be_bytes());
+            writer.write_chunk(*b"acTL", &actl).unwrap();
+
+            let mut sequence: u32 = 0;
+            for (i, frame) in frames.iter().enumerate() {
+                let mut fctl = Vec::new();
+                for value in [sequence, frame.width, frame.height, frame.left, frame.top].iter() {
+                    fctl.extend_from_slice(&value.to_be_bytes());
+                }
+                fctl.extend_from_slice(&frame.delay.to_be_bytes());
+                fctl.extend_from_slice(&100u16.to_be_bytes());
+                fctl.push(0); // APNG_DISPOSE_OP_NONE
+                fctl.push(0); // APNG_BLEND_OP_SOURCE
+                writer.write_chunk(*b"fcTL", &fctl).unwrap();
+                sequence += 1;
+
+                if i == 0 {
+                    writer.write_chunk(*b"IDAT", &frame.data).unwrap();
+                } else {
+                    let mut fdat = sequence.to_be_bytes().to_vec();
+                    fdat.extend_from_slice(&frame.data);
+                    writer.write_chunk(*b"fdAT", &fdat).unwrap();
+                    sequence += 1;
+                }
+            }
+        } // IEND is written when the writer is dropped
+        buffer
+    }
+}
+
+// the rectangle (left, top, width, height) that contains all the changed
+// pixels. If nothing changed, a pixel at the left top.
+fn changed_rect(previous: &Image, image: &Image) -> (u32, u32, u32, u32) {
+    let (mut x0, mut y0, mut x1, mut y1) = (image.width, image.height, 0, 0);
+    for y in 0..image.height {
+        for x in 0..image.width {
+            if previous.pixel(x, y) != image.pixel(x, y) {
+                x0 = x0.min(x);
+                y0 = y0.min(y);
+                x1 = x1.max(x + 1);
+                y1 = y1.max(y + 1);
+            }
+        }
+    }
+    if x1 == 0 {
+        (0, 0, 1, 1)
+    } else {
+        (x0, y0, x1 - x0, y1 - y0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Board, Color};
+    use crate::render::{self, RenderOptions};
+
+    #[test]
+    fn encode_animation() {
+        let mut board = Board::new(5);
+        let mut images = vec![render::render(&board, &RenderOptions::default())];
+        for turn in [Color::Red, Color::Blue].iter() {
+            let mv = board.possible_moves(*turn)[0];
+            board.apply_move(mv, *turn);
+            images.push(render::render(&board, &RenderOptions::default()));
+        }
+        let mut encoder = ApngEncoder::new(0);
+        for image in images.iter() {
+            encoder.add_frame(image, 50).unwrap();
+        }
+        assert!(encoder.add_frame(&images[0].scaled(0.5), 50).is_err());
+        encoder.set_last_delay(200);
+        let apng = encoder.finish();
+        assert!(encoder.is_empty());
+
+        // put the frames together and compare them with the images
+        let (info, mut reader) = png::Decoder::new(&apng[..]).read_info().unwrap();
+        assert_eq!((info.width, info.height), (render::IMAGE_WIDTH, render::IMAGE_HEIGHT));
+        assert_eq!(reader.info().animation_control().unwrap().num_frames, 3);
+        let mut canvas = Image::new(info.width, info.height, [0; 4]);
+        let mut buffer = vec![0; info.buffer_size()];
+        for (i, image) in images.iter().enumerate() {
+            reader.next_frame(&mut buffer).unwrap();
+            let fc = *reader.info().frame_control().unwrap();
+            assert_eq!(fc.delay_num, if i == 2 {200} else {50});
+            if 0 < i {
+                assert!(fc.width * fc.height < info.width * info.height);
+            }
+            for y in 0..fc.height {
+                for x in 0..fc.width {
+                    let src = ((y * fc.width + x) * 4) as usize;
+                    let dst = (((fc.y_offset + y) * info.width + fc.x_offset + x) * 4) as usize;
+                    canvas.pixels[dst..dst + 4].copy_from_slice(&buffer[src..src + 4]);
+                }
+            }
+            assert!(canvas == *image);
+        }
+    }
+}
@@ -0,0 +1,69 @@
+// Build an opening book (see `separo_rs::book`) from game records, e.g. the
+// archives of separo-tournament or separo-selfplay.
+//
+//     $ separo-book [OPTIONS] ARCHIVE... > book.bin
+//
+//     --size N       board size (default: 9)
+//     --depth N      moves to store, passes excluded (default: 8)
+//     --min-games N  drop the moves played less than N times (default: 1)
+
+use separo_rs::book::OpeningBook;
+use separo_rs::game_record::GameRecord;
+
+use std::fs;
+use std::io::{self, Write};
+use std::process;
+
+fn usage(program: &str) -> ! {
+    eprintln!("usage: {} [--size N] [--depth N] [--min-games N] ARCHIVE...", program);
+    process::exit(1);
+}
+
+fn main() {
+    let args = std::env::args().collect::<Vec<_>>();
+    let mut size      = 9;
+    let mut depth     = 8;
+    let mut min_games = 1;
+    let mut archives  = Vec::new();
+
+    let mut iter = args.iter().skip(1);
+    while let Some(arg) = iter.next() {
+        let mut value = || iter.next().cloned().unwrap_or_else(|| usage(&args[0]));
+        match arg.as_str() {
+            "--size"      => size      = value().parse().unwrap_or_else(|_| usage(&args[0])),
+            "--depth"     => depth     = value().parse().unwrap_or_else(|_| usage(&args[0])),
+            "--min-games" => min_games = value().parse().unwrap_or_else(|_| usage(&args[0])),
+            path => archives.push(path.to_string()),
+        }
+    }
+    if archives.is_empty() || !(4..20).contains(&size) {
+        usage(&args[0]);
+    }
+
+    let mut book = OpeningBook::new(size, depth);
+    let mut games = 0;
+    for path in archives.iter() {
+        let records = fs::read_to_string(path).map_err(|e| e.to_string())
+            .and_then(|text| GameRecord::parse_all(&text))
+            .unwrap_or_else(|err| {
+                eprintln!("cannot read {}: {}", path, err);
+                process::exit(1);
+            });
+        for record in records.iter() {
+            if let Err(err) = book.add_record(record) {
+                eprintln!("{}: {}", path, err);
+                process::exit(1);
+            }
+            games += (record.size == size) as usize;
+        }
+    }
+    book.prune(min_games);
+    eprintln!("{} positions from {} games", book.len(), games);
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    if let Err(err) = out.write_all(&book.to_bytes()).and_then(|_| out.flush()) {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,113 @@
+// Search game records (see `separo_rs::database`).
+//
+//     $ separo-db [OPTIONS] QUERY [ARGS...]
+//
+//     --archive FILE   read the game records in FILE (can be repeated)
+//     --size N         board size of the position (default: 9)
+//
+// Queries:
+//
+//     games [MOVE...]          games that reached the position after the moves
+//     next [MOVE...]           moves played from the position and their results
+//     regions COLOR N          games where COLOR finished with N or more regions
+//
+// MOVE is written as in the game records, e.g. `a1-b2-b3` or `pass`. Red
+// moves first and the players alternate. A position is matched modulo the
+// symmetries of the board.
+
+use separo_rs::Color;
+use separo_rs::database::GameDatabase;
+use separo_rs::game_record::GameRecord;
+
+use std::fs;
+use std::process;
+
+fn usage(program: &str) -> ! {
+    eprintln!("usage: {} [--archive FILE]... [--size N] \
+               (games [MOVE...] | next [MOVE...] | regions COLOR N)", program);
+    process::exit(1);
+}
+
+// the position after the moves, checked by replaying them
+fn position(size: usize, moves: &[String]) -> Result<GameRecord, String> {
+    let mut record = GameRecord::new(size, String::new(), String::new());
+    for mv in moves.iter() {
+        record.moves.push(if mv == "pass" {None} else {Some(mv.parse()?)});
+    }
+    record.replay()?;
+    Ok(record)
+}
+
+fn print_game(db: &GameDatabase, id: usize) {
+    let record = db.game(id);
+    let (red, blue) = record.scores().unwrap();
+    let result = match record.winner().unwrap() {
+        Some(color) => format!("{} wins", color),
+        None        => "draw".to_string(),
+    };
+    println!("{:>5} {} vs {}, {}-{}, {}", id + 1, record.red, record.blue, red, blue, result);
+}
+
+fn main() {
+    let args = std::env::args().collect::<Vec<_>>();
+    let mut db   = GameDatabase::new();
+    let mut size = 9;
+
+    let mut iter = args.iter().skip(1);
+    let query = loop {
+        let arg = iter.next().unwrap_or_else(|| usage(&args[0]));
+        let mut value = || iter.next().cloned().unwrap_or_else(|| usage(&args[0]));
+        match arg.as_str() {
+            "--size"    => size = value().parse().unwrap_or_else(|_| usage(&args[0])),
+            "--archive" => {
+                let path = value();
+                let added = fs::read_to_string(&path).map_err(|e| e.to_string())
+                    .and_then(|text| db.import(&text));
+                if let Err(err) = added {
+                    eprintln!("cannot read {}: {}", path, err);
+                    process::exit(1);
+                }
+            }
+            query => break query.to_string(),
+        }
+    };
+    let rest = iter.cloned().collect::<Vec<_>>();
+    if !(4..20).contains(&size) {
+        usage(&args[0]);
+    }
+    eprintln!("{} games", db.len());
+
+    match query.as_str() {
+        "games" | "next" => {
+            let record = position(size, &rest).unwrap_or_else(|err| {
+                eprintln!("{}", err);
+                process::exit(1);
+            });
+            let board = record.final_board().unwrap();
+            if query == "games" {
+                for id in db.games_reaching(&board) {
+                    print_game(&db, id);
+                }
+            } else {
+                let turn = GameRecord::turn_of(record.moves.len());
+                println!("{:>12} {:>6} {:>6} {:>6} {:>9}", "move", "games", "wins", "draws", "win rate");
+                for c in db.continuations(&board, turn) {
+                    let mv = c.mv.map_or("pass".to_string(), |m| m.to_string());
+                    println!("{:>12} {:>6} {:>6} {:>6} {:>8.1}%", mv, c.games, c.wins, c.draws,
+                             c.win_rate() * 100.0);
+                }
+            }
+        }
+        "regions" => {
+            if rest.len() != 2 {
+                usage(&args[0]);
+            }
+            let color: Color = rest[0].parse().unwrap_or_else(|_| usage(&args[0]));
+            let n = rest[1].parse().unwrap_or_else(|_| usage(&args[0]));
+            for id in db.games_with_regions(color, n) {
+                print_game(&db, id);
+            }
+        }
+        _ => usage(&args[0]),
+    }
+}
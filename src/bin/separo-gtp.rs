@@ -0,0 +1,41 @@
+// Play Separo through a GTP-style text protocol on stdin/stdout.
+//
+//     $ separo-gtp [ENGINE] [SEED]
+//
+// ENGINE is an engine spec like `uct:time=2` (see `separo_rs::engine`).
+
+use separo_rs::engine::EngineConfig;
+use separo_rs::gtp::Gtp;
+
+use std::io;
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn main() {
+    let args = std::env::args().collect::<Vec<_>>();
+
+    let config = match args.get(1).map(|spec| spec.parse::<EngineConfig>()) {
+        Some(Ok(config)) => config,
+        Some(Err(err)) => {
+            eprintln!("{}", err);
+            eprintln!("usage: {} [ENGINE] [SEED]", args[0]);
+            process::exit(1);
+        }
+        None => EngineConfig::default(),
+    };
+    let seed = match args.get(2).map(|s| s.parse::<u64>()) {
+        Some(Ok(seed)) => seed,
+        Some(Err(_)) => {
+            eprintln!("invalid seed: {}", args[2]);
+            process::exit(1);
+        }
+        None => SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64,
+    };
+
+    let stdin  = io::stdin();
+    let stdout = io::stdout();
+    if let Err(err) = Gtp::new(config, seed).run(stdin.lock(), stdout.lock()) {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
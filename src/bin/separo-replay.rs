@@ -0,0 +1,90 @@
+// Render a game record, e.g. a game in a tournament archive, into an
+// animation (see `separo_rs::recorder`) without playing it again.
+//
+//     $ separo-replay [OPTIONS] ARCHIVE OUTPUT
+//
+//     --game N          the N-th game in the archive (default: 1)
+//     --format FORMAT   gif, apng or sheet (a contact sheet PNG) (default: gif)
+//     --delay N         delay of each frame in 1/100 seconds (default: 100)
+//     --final-delay N   delay of the final position (default: 300)
+//     --loops N         times to play the animation, 0 means forever (default: 0)
+//     --scale X         size relative to the board images (default: 1)
+//     --theme THEME     colors, e.g. dark or light,background=#f0e0c0 (see
+//                       `separo_rs::render::Theme`) (default: light)
+//     --guide           draw the possible moves
+
+use separo_rs::game_record::GameRecord;
+use separo_rs::recorder::{GifOptions, RecordFormat, Recorder};
+
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::process;
+
+fn usage(program: &str) -> ! {
+    eprintln!("usage: {} [--game N] [--format gif|apng|sheet] [--delay N] [--final-delay N] \
+               [--loops N] [--scale X] [--theme THEME] [--guide] ARCHIVE OUTPUT", program);
+    process::exit(1);
+}
+
+fn main() {
+    let args = std::env::args().collect::<Vec<_>>();
+    let mut game    = 1;
+    let mut guide   = false;
+    let mut options = GifOptions{final_delay: 300, ..GifOptions::new()};
+    let mut paths   = Vec::new();
+
+    let mut iter = args.iter().skip(1);
+    while let Some(arg) = iter.next() {
+        let mut value = || iter.next().cloned().unwrap_or_else(|| usage(&args[0]));
+        match arg.as_str() {
+            "--game"        => game                = value().parse().unwrap_or_else(|_| usage(&args[0])),
+            "--delay"       => options.delay       = value().parse().unwrap_or_else(|_| usage(&args[0])),
+            "--final-delay" => options.final_delay = value().parse().unwrap_or_else(|_| usage(&args[0])),
+            "--loops"       => options.loops       = value().parse().unwrap_or_else(|_| usage(&args[0])),
+            "--scale"       => options.scale       = value().parse().unwrap_or_else(|_| usage(&args[0])),
+            "--theme"       => options.theme       = value().parse().unwrap_or_else(|err| {
+                eprintln!("{}", err);
+                usage(&args[0])
+            }),
+            "--guide"       => guide = true,
+            "--format"      => options.format = match value().as_str() {
+                "gif"   => RecordFormat::Gif,
+                "apng"  => RecordFormat::Apng,
+                "sheet" => RecordFormat::ContactSheet,
+                _       => usage(&args[0]),
+            },
+            path => paths.push(path.to_string()),
+        }
+    }
+    if paths.len() != 2 || game == 0 {
+        usage(&args[0]);
+    }
+    if let Err(err) = options.check() {
+        eprintln!("{}", err);
+        usage(&args[0]);
+    }
+
+    let records = fs::read_to_string(&paths[0]).map_err(|e| e.to_string())
+        .and_then(|text| GameRecord::parse_all(&text))
+        .unwrap_or_else(|err| {
+            eprintln!("cannot read {}: {}", paths[0], err);
+            process::exit(1);
+        });
+    let record = records.get(game - 1).unwrap_or_else(|| {
+        eprintln!("{} has only {} games", paths[0], records.len());
+        process::exit(1);
+    });
+
+    let file = File::create(&paths[1]).unwrap_or_else(|err| {
+        eprintln!("cannot create {}: {}", paths[1], err);
+        process::exit(1);
+    });
+    let mut recorder = Recorder::new(BufWriter::new(file), options).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        process::exit(1);
+    });
+    if let Err(err) = recorder.add_record(record, guide).and_then(|_| recorder.finish()) {
+        eprintln!("cannot write {}: {}", paths[1], err);
+        process::exit(1);
+    }
+}
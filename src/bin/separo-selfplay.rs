@@ -0,0 +1,64 @@
+// Generate self-play data as JSON lines (see `separo_rs::selfplay`).
+//
+//     $ separo-selfplay [OPTIONS] > data.jsonl
+//
+//     --games N              number of games (default: 1)
+//     --size N               board size (default: 9)
+//     --playouts N           playouts per move (default: 1000)
+//     --temperature X        temperature of the move sampling (default: 1.0)
+//     --temperature-moves N  moves sampled with temperature (default: 8)
+//     --seed N               seed (default: 0)
+//     --archive FILE         write the game records to FILE
+
+use separo_rs::selfplay::SelfPlay;
+
+use std::fs;
+use std::io;
+use std::process;
+
+fn usage(program: &str) -> ! {
+    eprintln!("usage: {} [--games N] [--size N] [--playouts N] [--temperature X] \
+               [--temperature-moves N] [--seed N] [--archive FILE]", program);
+    process::exit(1);
+}
+
+fn main() {
+    let args = std::env::args().collect::<Vec<_>>();
+    let mut selfplay = SelfPlay::default();
+    let mut games    = 1;
+    let mut archive  = None;
+
+    let mut iter = args.iter().skip(1);
+    while let Some(arg) = iter.next() {
+        let value = iter.next().unwrap_or_else(|| usage(&args[0]));
+        match arg.as_str() {
+            "--games"             => games = value.parse().unwrap_or_else(|_| usage(&args[0])),
+            "--size"              => selfplay.size = value.parse().unwrap_or_else(|_| usage(&args[0])),
+            "--playouts"          => selfplay.playouts = value.parse().unwrap_or_else(|_| usage(&args[0])),
+            "--temperature"       => selfplay.temperature = value.parse().unwrap_or_else(|_| usage(&args[0])),
+            "--temperature-moves" => selfplay.temperature_moves = value.parse().unwrap_or_else(|_| usage(&args[0])),
+            "--seed"              => selfplay.seed = value.parse().unwrap_or_else(|_| usage(&args[0])),
+            "--archive"           => archive = Some(value.clone()),
+            _ => usage(&args[0]),
+        }
+    }
+    if !(4..20).contains(&selfplay.size) {
+        usage(&args[0]);
+    }
+
+    let stdout = io::stdout();
+    let mut records = Vec::new();
+    match selfplay.generate(games, stdout.lock(), |record| records.push(record.to_string())) {
+        Ok(count) => eprintln!("{} positions from {} games", count, games),
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+    }
+    if let Some(path) = archive {
+        if let Err(err) = fs::write(&path, records.join("\n")) {
+            eprintln!("cannot write {}: {}", path, err);
+            process::exit(1);
+        }
+    }
+}
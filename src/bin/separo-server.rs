@@ -0,0 +1,26 @@
+// Serve game rooms over WebSocket.
+//
+//     $ separo-server [ADDRESS]
+//
+// ADDRESS defaults to 127.0.0.1:8080. See `separo_rs::server` for the messages.
+
+use separo_rs::server::Server;
+
+use std::process;
+
+fn main() {
+    let addr = std::env::args().nth(1).unwrap_or_else(|| "127.0.0.1:8080".to_string());
+
+    let server = match Server::bind(&addr) {
+        Ok(server) => server,
+        Err(err) => {
+            eprintln!("cannot listen on {}: {}", addr, err);
+            process::exit(1);
+        }
+    };
+    eprintln!("listening on ws://{}/", server.local_addr().unwrap());
+    if let Err(err) = server.run() {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
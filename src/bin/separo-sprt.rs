@@ -0,0 +1,91 @@
+// Play the new engine against the base engine until SPRT decides whether the
+// new one is stronger. It can be used as a regression gate:
+//
+//     $ separo-sprt [OPTIONS] NEW BASE && echo "no regression"
+//
+//     --elo0 X          Elo difference of H0 (default: 0)
+//     --elo1 X          Elo difference of H1 (default: 10)
+//     --alpha X         false positive rate (default: 0.05)
+//     --beta X          false negative rate (default: 0.05)
+//     --size N          board size (default: 9)
+//     --max-games N     give up after N games (default: 10000)
+//     --seed N          seed of the match (default: 0)
+//
+// The exit status is 0 if H1 is accepted, 1 if H0 is accepted, 2 if it is not
+// decided within max-games, and 3 if the arguments are invalid.
+
+use separo_rs::Color;
+use separo_rs::engine::EngineConfig;
+use separo_rs::sprt::{Sprt, SprtStatus};
+use separo_rs::tournament::Elo;
+
+use std::process;
+
+fn usage(program: &str) -> ! {
+    eprintln!("usage: {} [--elo0 X] [--elo1 X] [--alpha X] [--beta X] [--size N] \
+               [--max-games N] [--seed N] NEW BASE", program);
+    process::exit(3);
+}
+
+fn main() {
+    let args = std::env::args().collect::<Vec<_>>();
+    let mut sprt      = Sprt::default();
+    let mut size      = 9;
+    let mut max_games = 10000;
+    let mut seed      = 0;
+    let mut engines   = Vec::new();
+
+    let mut iter = args.iter().skip(1);
+    while let Some(arg) = iter.next() {
+        let mut value = || iter.next().cloned().unwrap_or_else(|| usage(&args[0]));
+        match arg.as_str() {
+            "--elo0"      => sprt.elo0  = value().parse().unwrap_or_else(|_| usage(&args[0])),
+            "--elo1"      => sprt.elo1  = value().parse().unwrap_or_else(|_| usage(&args[0])),
+            "--alpha"     => sprt.alpha = value().parse().unwrap_or_else(|_| usage(&args[0])),
+            "--beta"      => sprt.beta  = value().parse().unwrap_or_else(|_| usage(&args[0])),
+            "--size"      => size       = value().parse().unwrap_or_else(|_| usage(&args[0])),
+            "--max-games" => max_games  = value().parse().unwrap_or_else(|_| usage(&args[0])),
+            "--seed"      => seed       = value().parse().unwrap_or_else(|_| usage(&args[0])),
+            spec => match spec.parse::<EngineConfig>() {
+                Ok(config) => engines.push(config),
+                Err(err) => {
+                    eprintln!("{}", err);
+                    usage(&args[0]);
+                }
+            },
+        }
+    }
+    if engines.len() != 2 || !(4..20).contains(&size) || sprt.elo1 <= sprt.elo0 {
+        usage(&args[0]);
+    }
+    // fail early, e.g. if a weight file does not match the board size
+    for config in engines.iter() {
+        if let Err(err) = config.build(Color::Red, 0, size) {
+            eprintln!("{}: {}", config, err);
+            process::exit(3);
+        }
+    }
+
+    let (lower, upper) = sprt.bounds();
+    println!("H0: elo = {}, H1: elo = {}, LLR bounds = [{:.2}, {:.2}]",
+             sprt.elo0, sprt.elo1, lower, upper);
+    let (status, tally) = sprt.run(&engines[0], &engines[1], size, seed, max_games,
+        |_, tally, llr| {
+            println!("game {:>5}: W {} D {} L {}, LLR {:+.2}",
+                     tally.games(), tally.wins, tally.draws, tally.losses, llr);
+        });
+
+    println!("{} vs {}: {}", engines[0], engines[1],
+             Elo::new(tally.wins, tally.draws, tally.losses));
+    match status {
+        SprtStatus::AcceptH1 => println!("H1 accepted"),
+        SprtStatus::AcceptH0 => {
+            println!("H0 accepted");
+            process::exit(1);
+        }
+        SprtStatus::Continue => {
+            println!("not decided in {} games", max_games);
+            process::exit(2);
+        }
+    }
+}
@@ -0,0 +1,84 @@
+// Run a round robin (or a gauntlet) between engines and print the crosstable.
+//
+//     $ separo-tournament [OPTIONS] ENGINE ENGINE...
+//
+//     --gauntlet        the first engine plays against each of the others
+//     --size N          board size (default: 9)
+//     --games N         games per pair (default: 2)
+//     --seed N          seed of the tournament (default: 0)
+//     --archive FILE    write the game records to FILE
+//
+// ENGINE is an engine spec like `uct:time=1,coeff=1.5` (see `separo_rs::engine`).
+
+use separo_rs::Color;
+use separo_rs::engine::EngineConfig;
+use separo_rs::tournament::{Pairing, Tournament};
+
+use std::fs;
+use std::process;
+
+fn usage(program: &str) -> ! {
+    eprintln!("usage: {} [--gauntlet] [--size N] [--games N] [--seed N] [--archive FILE] \
+               ENGINE ENGINE...", program);
+    process::exit(1);
+}
+
+fn main() {
+    let args = std::env::args().collect::<Vec<_>>();
+    let mut tournament = Tournament{
+        engines: Vec::new(),
+        pairing: Pairing::RoundRobin,
+        size:    9,
+        games:   2,
+        seed:    0,
+    };
+    let mut archive = None;
+
+    let mut iter = args.iter().skip(1);
+    while let Some(arg) = iter.next() {
+        let mut value = || iter.next().cloned().unwrap_or_else(|| usage(&args[0]));
+        match arg.as_str() {
+            "--gauntlet" => tournament.pairing = Pairing::Gauntlet,
+            "--size"     => tournament.size  = value().parse().unwrap_or_else(|_| usage(&args[0])),
+            "--games"    => tournament.games = value().parse().unwrap_or_else(|_| usage(&args[0])),
+            "--seed"     => tournament.seed  = value().parse().unwrap_or_else(|_| usage(&args[0])),
+            "--archive"  => archive = Some(value()),
+            spec => match spec.parse::<EngineConfig>() {
+                Ok(config) => tournament.engines.push(config),
+                Err(err) => {
+                    eprintln!("{}", err);
+                    usage(&args[0]);
+                }
+            },
+        }
+    }
+    if tournament.engines.len() < 2 || !(4..20).contains(&tournament.size) {
+        usage(&args[0]);
+    }
+    // fail early, e.g. if a weight file does not match the board size
+    for config in tournament.engines.iter() {
+        if let Err(err) = config.build(Color::Red, 0, tournament.size) {
+            eprintln!("{}: {}", config, err);
+            process::exit(1);
+        }
+    }
+
+    let total = tournament.pairs().len() * tournament.games;
+    let mut played = 0;
+    let result = tournament.run(|record| {
+        played += 1;
+        let result = match record.winner().unwrap() {
+            Some(color) => format!("{} wins", color),
+            None        => "draw".to_string(),
+        };
+        eprintln!("game {}/{}: {} vs {}, {}", played, total, record.red, record.blue, result);
+    });
+
+    print!("{}", result);
+    if let Some(path) = archive {
+        if let Err(err) = fs::write(&path, result.archive()) {
+            eprintln!("cannot write {}: {}", path, err);
+            process::exit(1);
+        }
+    }
+}
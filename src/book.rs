@@ -0,0 +1,383 @@
+// An opening book built from game records, e.g. a tournament archive or the
+// records of self-play.
+//
+// For each position (the Zobrist hash of the board and the player to move) in
+// the first `depth` moves of the games, the book keeps how many times each
+// move was played and how many of those games the mover won or drew. An engine
+// consults the book first (see `BookEngine`) and searches only when the
+// position is out of the book. A move is chosen at random with probability
+// proportional to its points (wins + draws / 2), so that popular and
+// successful moves are played more often and the games do not repeat. The
+// depth counts the moves on the board, `Board::num_moves`, so passes do not
+// count, and a position is in the book if it has less than `depth` moves.
+//
+// The book is serialized in a compact binary format (little-endian), small
+// enough to ship with the web build, e.g. via `include_bytes!` or fetch.
+//
+//     magic    4 bytes, "SPBK"
+//     version  u32, 1
+//     size     u32, the board size
+//     depth    u32, the number of moves stored
+//     entries  u32, the number of positions
+//     for each position, in the ascending order of the key:
+//         key     u64, the Zobrist hash (see `position_key`)
+//         moves   u16, the number of moves
+//         for each move:
+//             index   u16, `Move::policy_index`
+//             games   u32
+//             wins    u32
+//             draws   u32
+
+use crate::{Board, Color, Move, zobrist_key};
+use crate::engine::{Engine, SearchInfo};
+use crate::game_record::GameRecord;
+
+use rand::prelude::*;
+use wasm_bindgen::prelude::*;
+
+use std::collections::BTreeMap;
+
+const MAGIC:   &[u8; 4] = b"SPBK";
+const VERSION: u32      = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MoveStats {
+    pub mv:    Move,
+    pub games: u32,
+    pub wins:  u32, // of the player who played the move
+    pub draws: u32,
+}
+
+impl MoveStats {
+    pub fn points(&self) -> f64 {
+        self.wins as f64 + 0.5 * self.draws as f64
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpeningBook {
+    size:    usize,
+    depth:   usize,
+    entries: BTreeMap<u64, Vec<MoveStats>>,
+}
+
+// the key of a position in the book
+pub fn position_key(board: &Board, turn: Color) -> u64 {
+    match turn {
+        Color::Red  => board.zobrist_hash(),
+        Color::Blue => board.zobrist_hash() ^ zobrist_key(u64::MAX - 1),
+    }
+}
+
+impl OpeningBook {
+    pub fn new(size: usize, depth: usize) -> Self {
+        OpeningBook{size, depth, entries: BTreeMap::new()}
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    // the number of positions in the book
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // adds the first `depth` moves of a game. Records of other board sizes
+    // are ignored.
+    pub fn add_record(&mut self, record: &GameRecord) -> Result<(), String> {
+        if record.size != self.size {
+            return Ok(());
+        }
+        let boards = record.replay()?;
+        let winner = record.winner()?;
+        for (ply, next_move) in record.moves.iter().enumerate() {
+            if self.depth <= boards[ply].num_moves() {
+                break;
+            }
+            let next_move = match next_move {
+                Some(next_move) => *next_move,
+                None            => continue,
+            };
+            let turn  = GameRecord::turn_of(ply);
+            let moves = self.entries.entry(position_key(&boards[ply], turn)).or_default();
+            let index = match moves.iter().position(|s| s.mv == next_move) {
+                Some(index) => index,
+                None => {
+                    moves.push(MoveStats{mv: next_move, games: 0, wins: 0, draws: 0});
+                    moves.len() - 1
+                }
+            };
+            moves[index].games += 1;
+            match winner {
+                Some(color) if color == turn => moves[index].wins  += 1,
+                None                         => moves[index].draws += 1,
+                Some(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    pub fn from_records(records: &[GameRecord], size: usize, depth: usize)
+        -> Result<Self, String> {
+        let mut book = OpeningBook::new(size, depth);
+        for record in records.iter() {
+            book.add_record(record)?;
+        }
+        Ok(book)
+    }
+
+    // removes the moves played less than `min_games` times, and the positions
+    // that have no move left.
+    pub fn prune(&mut self, min_games: u32) {
+        for moves in self.entries.values_mut() {
+            moves.retain(|s| min_games <= s.games);
+        }
+        self.entries.retain(|_, moves| !moves.is_empty());
+    }
+
+    pub fn lookup(&self, board: &Board, turn: Color) -> Option<&[MoveStats]> {
+        if board.width as usize != self.size || self.depth <= board.num_moves() {
+            return None;
+        }
+        self.entries.get(&position_key(board, turn)).map(|moves| moves.as_slice())
+    }
+
+    // chooses a move in proportion to its points. None if the position is not
+    // in the book or no move has scored.
+    pub fn choose<R: Rng>(&self, board: &Board, turn: Color, rng: &mut R) -> Option<Move> {
+        // moves are checked in case of a hash collision
+        let moves = self.lookup(board, turn)?.iter()
+            .filter(|s| 0.0 < s.points() && board.is_valid_move(s.mv, turn))
+            .collect::<Vec<_>>();
+        if moves.is_empty() {
+            return None;
+        }
+        let total = moves.iter().map(|s| s.points()).sum::<f64>();
+        let mut r = rng.gen_range(0.0, total);
+        for stats in moves.iter() {
+            if r < stats.points() {
+                return Some(stats.mv);
+            }
+            r -= stats.points();
+        }
+        moves.last().map(|s| s.mv)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = MAGIC.to_vec();
+        for v in [VERSION, self.size as u32, self.depth as u32, self.entries.len() as u32].iter() {
+            out.extend_from_slice(&v.to_le_bytes());
+        }
+        for (key, moves) in self.entries.iter() {
+            out.extend_from_slice(&key.to_le_bytes());
+            out.extend_from_slice(&(moves.len() as u16).to_le_bytes());
+            for stats in moves.iter() {
+                let index = stats.mv.policy_index(self.size).unwrap() as u16;
+                out.extend_from_slice(&index.to_le_bytes());
+                for v in [stats.games, stats.wins, stats.draws].iter() {
+                    out.extend_from_slice(&v.to_le_bytes());
+                }
+            }
+        }
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let mut pos = 0;
+        let mut take = |n: usize| -> Result<&[u8], String> {
+            if bytes.len() < pos + n {
+                return Err("unexpected end of the book".to_string());
+            }
+            pos += n;
+            Ok(&bytes[pos - n..pos])
+        };
+        if take(4)? != MAGIC {
+            return Err("not an opening book".to_string());
+        }
+        let mut u32s = [0u32; 4];
+        for v in u32s.iter_mut() {
+            let b = take(4)?;
+            *v = u32::from_le_bytes([b[0], b[1], b[2], b[3]]);
+        }
+        let [version, size, depth, entries] = u32s;
+        if version != VERSION {
+            return Err(format!("unsupported version: {}", version));
+        }
+        let size = size as usize;
+        if !(4..20).contains(&size) {
+            return Err(format!("unsupported board size: {}", size));
+        }
+
+        let mut book = OpeningBook::new(size, depth as usize);
+        for _ in 0..entries {
+            let b   = take(8)?;
+            let key = u64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]);
+            let b   = take(2)?;
+            let num = u16::from_le_bytes([b[0], b[1]]);
+            let mut moves = Vec::with_capacity(num as usize);
+            for _ in 0..num {
+                let b = take(14)?;
+                let index = u16::from_le_bytes([b[0], b[1]]) as usize;
+                let mv = Move::from_policy_index(index, size)
+                    .ok_or_else(|| format!("invalid move index: {}", index))?;
+                let u32_at = |i: usize| u32::from_le_bytes([b[i], b[i+1], b[i+2], b[i+3]]);
+                moves.push(MoveStats{mv, games: u32_at(2), wins: u32_at(6), draws: u32_at(10)});
+            }
+            book.entries.insert(key, moves);
+        }
+        if pos != bytes.len() {
+            return Err(format!("{} bytes left after the book", bytes.len() - pos));
+        }
+        Ok(book)
+    }
+}
+
+#[wasm_bindgen]
+impl OpeningBook {
+    // reads a book from its binary form, e.g. fetched from the server.
+    #[wasm_bindgen(js_name = from_bytes)]
+    pub fn from_bytes_js(bytes: &[u8]) -> Result<OpeningBook, JsValue> {
+        OpeningBook::from_bytes(bytes).map_err(|e| JsValue::from_str(&e))
+    }
+
+    // plays a book move for `color`, or returns undefined if the position is
+    // out of the book. Then the caller lets the player search.
+    pub fn play(&self, board: &Board, color: Color, seed0: u32, seed1: u32) -> Option<Board> {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(crate::convert_seed(seed0, seed1));
+        let next_move = self.choose(board, color, &mut rng)?;
+        let mut board = board.clone();
+        board.apply_move(next_move, color);
+        Some(board)
+    }
+}
+
+// An engine that plays from the book while the position is in it, up to
+// `depth` moves on the board (or the depth of the book, if smaller).
+pub struct BookEngine {
+    book:    OpeningBook,
+    depth:   usize,
+    rng:     rand::rngs::StdRng,
+    engine:  Box<dyn Engine>,
+    in_book: bool, // whether the last move came from the book
+}
+
+impl BookEngine {
+    pub fn new(book: OpeningBook, depth: usize, seed: u64, engine: Box<dyn Engine>) -> Self {
+        BookEngine{book, depth, rng: rand::rngs::StdRng::seed_from_u64(seed), engine, in_book: false}
+    }
+}
+
+impl Engine for BookEngine {
+    fn color(&self) -> Color {
+        self.engine.color()
+    }
+    fn genmove(&mut self, board: &Board) -> Option<Move> {
+        let color = self.engine.color();
+        if board.num_moves() < self.depth {
+            if let Some(next_move) = self.book.choose(board, color, &mut self.rng) {
+                self.in_book = true;
+                return Some(next_move);
+            }
+        }
+        self.in_book = false;
+        self.engine.genmove(board)
+    }
+    fn has_resigned(&self) -> bool {
+        !self.in_book && self.engine.has_resigned()
+    }
+    // a book move costs no search
+    fn search_info(&self) -> Option<SearchInfo> {
+        if self.in_book {
+            Some(SearchInfo::default())
+        } else {
+            self.engine.search_info()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::EngineConfig;
+    use crate::tournament::play_game;
+
+    fn records() -> Vec<GameRecord> {
+        (0..20).map(|seed| play_game(&EngineConfig::Greedy{lookahead: true},
+                                     &EngineConfig::Random, 5, seed)).collect()
+    }
+
+    #[test]
+    fn book_from_records() {
+        let records = records();
+        let book = OpeningBook::from_records(&records, 5, 4).unwrap();
+        assert!(!book.is_empty());
+
+        // every game starts from the same position
+        let board = Board::new(5);
+        let first = book.lookup(&board, Color::Red).unwrap();
+        assert_eq!(first.iter().map(|s| s.games).sum::<u32>(), 20);
+        let red_wins = records.iter().filter(|r| r.winner().unwrap() == Some(Color::Red)).count();
+        assert_eq!(first.iter().map(|s| s.wins).sum::<u32>() as usize, red_wins);
+        assert!(book.lookup(&board, Color::Blue).is_none());
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+        for _ in 0..10 {
+            let mv = book.choose(&board, Color::Red, &mut rng).unwrap();
+            assert!(first.iter().any(|s| s.mv == mv && 0.0 < s.points()));
+        }
+
+        // out of the book after `depth` moves
+        let boards = records[0].replay().unwrap();
+        assert!(book.lookup(&boards[3], Color::Blue).is_some());
+        assert!(book.lookup(&boards[4], Color::Red).is_none());
+
+        // passes do not count. The move after a pass is on the same board, so
+        // it is in the book as long as the pass is.
+        let (record, ply) = records.iter()
+            .find_map(|r| r.moves.iter().position(|m| m.is_none()).map(|ply| (r, ply)))
+            .unwrap();
+        let boards = record.replay().unwrap();
+        let depth = boards[ply].num_moves() + 1;
+        let book = OpeningBook::from_records(std::slice::from_ref(record), 5, depth).unwrap();
+        let after_pass = record.moves[ply + 1].unwrap();
+        assert_eq!(book.lookup(&boards[ply + 1], GameRecord::turn_of(ply + 1)).unwrap()[0].mv,
+                   after_pass);
+
+        let bytes = book.to_bytes();
+        assert_eq!(OpeningBook::from_bytes(&bytes).unwrap(), book);
+        assert!(OpeningBook::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+
+        let mut pruned = book.clone();
+        pruned.prune(2);
+        assert!(pruned.len() < book.len());
+        assert!(pruned.entries.values().flatten().all(|s| 2 <= s.games));
+    }
+
+    #[test]
+    fn engine_plays_from_book() {
+        let book = OpeningBook::from_records(&records(), 5, 2).unwrap();
+        let engine = EngineConfig::Random.build(Color::Red, 0, 5).unwrap();
+        let mut engine = BookEngine::new(book.clone(), 2, 0, engine);
+
+        let board = Board::new(5);
+        let next_move = engine.genmove(&board).unwrap();
+        assert!(book.lookup(&board, Color::Red).unwrap().iter().any(|s| s.mv == next_move));
+        assert_eq!(engine.search_info(), Some(SearchInfo::default()));
+
+        // the book can be limited by the engine
+        let engine = EngineConfig::Greedy{lookahead: true}.build(Color::Red, 0, 5).unwrap();
+        let mut engine = BookEngine::new(book, 0, 0, engine);
+        engine.genmove(&board).unwrap();
+        assert_eq!(engine.search_info(), None);
+    }
+}
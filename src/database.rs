@@ -0,0 +1,196 @@
+// A database of game records to search games by position.
+//
+// Every position of every game is indexed by its canonical hash, i.e. the
+// Zobrist hash modulo the symmetries of the board (see `NUM_SYMMETRIES`), so
+// that a search also finds the games that reached a mirrored position. The
+// moves that followed a position are transformed into the frame of the
+// queried board before they are counted.
+
+use crate::{Board, Color, Move};
+use crate::game_record::GameRecord;
+
+use std::collections::HashMap;
+
+// where a position appears in the database
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Occurrence {
+    game:     usize,
+    ply:      usize,
+    symmetry: usize, // the symmetry that gives the canonical hash
+}
+
+// results after a move from a position
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Continuation {
+    pub mv:    Option<Move>, // None means pass
+    pub games: usize,
+    pub wins:  usize, // of the player who moved
+    pub draws: usize,
+}
+
+impl Continuation {
+    pub fn win_rate(&self) -> f64 {
+        (self.wins as f64 + 0.5 * self.draws as f64) / self.games as f64
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Summary {
+    scores: (u16, u16),
+    winner: Option<Color>,
+}
+
+#[derive(Default)]
+pub struct GameDatabase {
+    games:     Vec<GameRecord>,
+    summaries: Vec<Summary>,
+    index:     HashMap<u64, Vec<Occurrence>>,
+}
+
+impl GameDatabase {
+    pub fn new() -> Self {
+        GameDatabase::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.games.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.games.is_empty()
+    }
+
+    pub fn game(&self, id: usize) -> &GameRecord {
+        &self.games[id]
+    }
+
+    // adds a game and returns its id. It fails if the record is broken.
+    pub fn add(&mut self, record: GameRecord) -> Result<usize, String> {
+        let boards  = record.replay()?;
+        let summary = Summary{scores: record.scores()?, winner: record.winner()?};
+        let id = self.games.len();
+        for (ply, board) in boards.iter().enumerate() {
+            let (hash, symmetry) = board.canonical_hash();
+            self.index.entry(hash).or_default().push(Occurrence{game: id, ply, symmetry});
+        }
+        self.games.push(record);
+        self.summaries.push(summary);
+        Ok(id)
+    }
+
+    // adds all the records in a text, e.g. a tournament archive, and returns
+    // the number of games added.
+    pub fn import(&mut self, text: &str) -> Result<usize, String> {
+        let records = GameRecord::parse_all(text)?;
+        let count = records.len();
+        for record in records {
+            self.add(record)?;
+        }
+        Ok(count)
+    }
+
+    fn occurrences<'a>(&'a self, board: &Board) -> impl Iterator<Item = &'a Occurrence> {
+        let hash = board.canonical_hash().0;
+        self.index.get(&hash).into_iter().flatten()
+    }
+
+    // ids of the games that reached the position, in the ascending order.
+    pub fn games_reaching(&self, board: &Board) -> Vec<usize> {
+        let mut games = self.occurrences(board).map(|o| o.game).collect::<Vec<_>>();
+        games.dedup(); // occurrences of a game are added in a row
+        games
+    }
+
+    // the moves played from the position by `turn`, most played first. The
+    // moves are written in the frame of `board`.
+    pub fn continuations(&self, board: &Board, turn: Color) -> Vec<Continuation> {
+        let width = board.width as usize;
+        let query_symmetry = board.canonical_hash().1;
+        let mut result: Vec<Continuation> = Vec::new();
+        for occurrence in self.occurrences(board) {
+            let record = &self.games[occurrence.game];
+            if GameRecord::turn_of(occurrence.ply) != turn || record.moves.len() <= occurrence.ply {
+                continue;
+            }
+            // game frame -> canonical frame -> query frame
+            let mv = record.moves[occurrence.ply].map(|m| m
+                .transformed(occurrence.symmetry, width)
+                .transformed(query_symmetry, width));
+
+            let index = match result.iter().position(|c| c.mv == mv) {
+                Some(index) => index,
+                None => {
+                    result.push(Continuation{mv, games: 0, wins: 0, draws: 0});
+                    result.len() - 1
+                }
+            };
+            result[index].games += 1;
+            match self.summaries[occurrence.game].winner {
+                Some(color) if color == turn => result[index].wins  += 1,
+                None                         => result[index].draws += 1,
+                Some(_) => {}
+            }
+        }
+        result.sort_by_key(|c| std::cmp::Reverse(c.games));
+        result
+    }
+
+    // ids of the games where `color` finished with `regions` or more regions
+    pub fn games_with_regions(&self, color: Color, regions: u16) -> Vec<usize> {
+        self.summaries.iter().enumerate().filter(|(_, s)| {
+            let score = match color {
+                Color::Red  => s.scores.0,
+                Color::Blue => s.scores.1,
+            };
+            regions <= score
+        }).map(|(id, _)| id).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::EngineConfig;
+    use crate::tournament::play_game;
+
+    #[test]
+    fn search_positions() {
+        let mut db = GameDatabase::new();
+        let record = play_game(&EngineConfig::Greedy{lookahead: true}, &EngineConfig::Random, 5, 0);
+        db.import(&record.to_string()).unwrap();
+
+        // the same game, mirrored
+        let mut mirrored = record.clone();
+        mirrored.moves = record.moves.iter().map(|m| m.map(|m| m.transformed(2, 5))).collect();
+        db.add(mirrored).unwrap();
+        assert_eq!(db.len(), 2);
+
+        let initial = Board::new(5);
+        assert_eq!(db.games_reaching(&initial), vec![0, 1]);
+        let boards = record.replay().unwrap();
+        assert_eq!(db.games_reaching(&boards[3]), vec![0, 1]);
+
+        // both games continue with the same move in the frame of the query
+        let continuations = db.continuations(&boards[2], Color::Red);
+        assert_eq!(continuations.len(), 1);
+        assert_eq!(continuations[0].mv, record.moves[2]);
+        assert_eq!(continuations[0].games, 2);
+        let red_won = record.winner().unwrap() == Some(Color::Red);
+        assert_eq!(continuations[0].wins, if red_won {2} else {0});
+        assert!(db.continuations(&boards[2], Color::Blue).is_empty());
+
+        let (red, blue) = record.scores().unwrap();
+        assert_eq!(db.games_with_regions(Color::Red,  red), vec![0, 1]);
+        assert!(db.games_with_regions(Color::Blue, blue + 1).is_empty());
+        assert_eq!(db.games_with_regions(Color::Blue, 0).len(), 2);
+
+        // a reply that was not played. All the first moves are the same
+        // modulo symmetry, but the replies are not.
+        let unplayed = boards[1].possible_moves(Color::Blue).into_iter().map(|m| {
+            let mut board = boards[1].clone();
+            board.apply_move(m, Color::Blue);
+            board
+        }).find(|b| b.canonical_hash().0 != boards[2].canonical_hash().0).unwrap();
+        assert!(db.games_reaching(&unplayed).is_empty());
+    }
+}
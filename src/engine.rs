@@ -0,0 +1,378 @@
+// A common interface over the players, for the programs that drive a game
+// from outside of the browser (e.g. the GTP-style text protocol).
+//
+// The wasm players take a board and return the next board. Here, an engine
+// returns the move it chose, so that the caller can validate, record, or
+// send it somewhere else.
+
+use crate::{Board, Color, Move};
+use crate::{RandomPlayer, GreedyPlayer, NaiveMonteCarlo, UCTMonteCarlo,
+            AlphaBetaPlayer, LeveledPlayer};
+use crate::book::{BookEngine, OpeningBook};
+use crate::network::Network;
+use crate::puct::PUCTPlayer;
+
+use serde::Serialize;
+
+use std::fmt;
+use std::str::FromStr;
+
+// Statistics of the last search. Fields that do not apply to an engine are
+// left as None.
+//   - nodes:      number of playouts (Monte Carlo) or searched nodes (AlphaBeta).
+//   - depth:      depth of the last completed iteration of AlphaBeta.
+//   - evaluation: static evaluation of the chosen move by AlphaBeta.
+//   - win_rate:   estimated win rate of the chosen move by Monte Carlo.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SearchInfo {
+    pub nodes:      u64,
+    pub depth:      Option<u32>,
+    pub evaluation: Option<i32>,
+    pub win_rate:   Option<f64>,
+}
+
+pub trait Engine {
+    fn color(&self) -> Color;
+
+    // returns the next move, or None if it passes (or resigns).
+    fn genmove(&mut self, board: &Board) -> Option<Move>;
+
+    fn has_resigned(&self) -> bool {
+        false
+    }
+
+    // statistics of the last genmove. None if the engine does not search.
+    fn search_info(&self) -> Option<SearchInfo> {
+        None
+    }
+}
+
+impl Engine for RandomPlayer {
+    fn color(&self) -> Color { self.color }
+    fn genmove(&mut self, board: &Board) -> Option<Move> {
+        RandomPlayer::genmove(self, board)
+    }
+}
+
+impl Engine for GreedyPlayer {
+    fn color(&self) -> Color { self.color }
+    fn genmove(&mut self, board: &Board) -> Option<Move> {
+        GreedyPlayer::genmove(self, board)
+    }
+}
+
+impl Engine for NaiveMonteCarlo {
+    fn color(&self) -> Color { self.color }
+    fn genmove(&mut self, board: &Board) -> Option<Move> {
+        NaiveMonteCarlo::genmove(self, board)
+    }
+    fn has_resigned(&self) -> bool {
+        NaiveMonteCarlo::has_resigned(self)
+    }
+    fn search_info(&self) -> Option<SearchInfo> {
+        self.last_search.clone()
+    }
+}
+
+impl Engine for UCTMonteCarlo {
+    fn color(&self) -> Color { self.color }
+    fn genmove(&mut self, board: &Board) -> Option<Move> {
+        UCTMonteCarlo::genmove(self, board)
+    }
+    fn has_resigned(&self) -> bool {
+        UCTMonteCarlo::has_resigned(self)
+    }
+    fn search_info(&self) -> Option<SearchInfo> {
+        self.last_search.clone()
+    }
+}
+
+impl Engine for AlphaBetaPlayer {
+    fn color(&self) -> Color { self.color }
+    fn genmove(&mut self, board: &Board) -> Option<Move> {
+        AlphaBetaPlayer::genmove(self, board)
+    }
+    fn search_info(&self) -> Option<SearchInfo> {
+        self.last_search.clone()
+    }
+}
+
+impl Engine for LeveledPlayer {
+    fn color(&self) -> Color { self.color }
+    fn genmove(&mut self, board: &Board) -> Option<Move> {
+        LeveledPlayer::genmove(self, board)
+    }
+    fn search_info(&self) -> Option<SearchInfo> {
+        self.last_search.clone()
+    }
+}
+
+impl Engine for PUCTPlayer {
+    fn color(&self) -> Color { self.color }
+    fn genmove(&mut self, board: &Board) -> Option<Move> {
+        PUCTPlayer::genmove(self, board)
+    }
+    fn search_info(&self) -> Option<SearchInfo> {
+        self.last_search()
+    }
+}
+
+// The kind of an engine and its parameters, written in text as
+// `name:key=value,key=value`, e.g. `uct:time=2,coeff=1.5`. Omitted parameters
+// take the same default values as the web interface.
+//
+// - `random`
+// - `greedy:lookahead=true`
+// - `naive:time=1`
+// - `uct:time=1,coeff=2.0,expand=3`
+// - `alphabeta:depth=4,time=1`
+// - `leveled:level=5,time=1`
+// - `puct:weights=model.bin,time=1,playouts=800,cpuct=1.5`
+//
+// `time` is the time limit per move in seconds. `weights` is the path to a
+// weight file (see `network`) and is required for `puct`. `playouts=0` means
+// that only the time is limited.
+//
+// Any engine can play from an opening book (see `book`) with
+// `book=<path>` and optionally `bookdepth=<moves>`, e.g.
+// `uct:time=1,book=openings.bin,bookdepth=6`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EngineConfig {
+    Random,
+    Greedy{lookahead: bool},
+    Naive{time: u32},
+    UCT{time: u32, coeff: f64, expand: u32},
+    AlphaBeta{depth: u32, time: u32},
+    Leveled{level: u32, time: u32},
+    PUCT{weights: String, time: u32, playouts: u32, cpuct: f64},
+    Book{book: String, depth: Option<u32>, engine: Box<EngineConfig>},
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        EngineConfig::UCT{time: 1, coeff: 2.0, expand: 3}
+    }
+}
+
+impl EngineConfig {
+    // It fails if the weight file of `puct` or the book cannot be loaded, or
+    // the network is trained for another board size.
+    pub fn build(&self, color: Color, seed: u64, board_width: usize) -> Result<Box<dyn Engine>, String> {
+        let seed0 = seed as u32;
+        let seed1 = (seed >> 32) as u32;
+        Ok(match *self {
+            EngineConfig::Random =>
+                Box::new(RandomPlayer::new(color, seed0, seed1)),
+            EngineConfig::Greedy{lookahead} =>
+                Box::new(GreedyPlayer::new(color, seed0, seed1, lookahead)),
+            EngineConfig::Naive{time} =>
+                Box::new(NaiveMonteCarlo::new(color, seed0, seed1, time)),
+            EngineConfig::UCT{time, coeff, expand} =>
+                Box::new(UCTMonteCarlo::new(color, seed0, seed1, time, coeff, expand, board_width)),
+            EngineConfig::AlphaBeta{depth, time} =>
+                Box::new(AlphaBetaPlayer::new(color, depth, time)),
+            EngineConfig::Leveled{level, time} =>
+                Box::new(LeveledPlayer::new(color, seed0, seed1, level, time, board_width)),
+            EngineConfig::PUCT{ref weights, time, playouts, cpuct} => {
+                let network = std::fs::read(weights).map_err(|e| e.to_string())
+                    .and_then(|bytes| Network::from_bytes(&bytes))
+                    .map_err(|e| format!("cannot load {}: {}", weights, e))?;
+                network.check_width(board_width).map_err(|e| format!("{}: {}", weights, e))?;
+                Box::new(PUCTPlayer::with_network(color, network, time, playouts, cpuct))
+            }
+            EngineConfig::Book{ref book, depth, ref engine} => {
+                let book = std::fs::read(book).map_err(|e| e.to_string())
+                    .and_then(|bytes| OpeningBook::from_bytes(&bytes))
+                    .map_err(|e| format!("cannot load {}: {}", book, e))?;
+                let depth = depth.map_or(book.depth(), |d| d as usize);
+                Box::new(BookEngine::new(book, depth, seed, engine.build(color, seed, board_width)?))
+            }
+        })
+    }
+
+    // overwrite the time limit per move, if the engine has one.
+    pub fn set_time_limit(&mut self, seconds: u32) {
+        match self {
+            EngineConfig::Naive{time}        |
+            EngineConfig::UCT{time, ..}       |
+            EngineConfig::AlphaBeta{time, ..} |
+            EngineConfig::Leveled{time, ..}   |
+            EngineConfig::PUCT{time, ..}      => *time = seconds,
+            EngineConfig::Book{engine, ..}    => engine.set_time_limit(seconds),
+            EngineConfig::Random | EngineConfig::Greedy{..} => {}
+        }
+    }
+}
+
+impl fmt::Display for EngineConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EngineConfig::Random =>
+                write!(f, "random"),
+            EngineConfig::Greedy{lookahead} =>
+                write!(f, "greedy:lookahead={}", lookahead),
+            EngineConfig::Naive{time} =>
+                write!(f, "naive:time={}", time),
+            EngineConfig::UCT{time, coeff, expand} =>
+                write!(f, "uct:time={},coeff={},expand={}", time, coeff, expand),
+            EngineConfig::AlphaBeta{depth, time} =>
+                write!(f, "alphabeta:depth={},time={}", depth, time),
+            EngineConfig::Leveled{level, time} =>
+                write!(f, "leveled:level={},time={}", level, time),
+            EngineConfig::PUCT{weights, time, playouts, cpuct} =>
+                write!(f, "puct:weights={},time={},playouts={},cpuct={}", weights, time, playouts, cpuct),
+            EngineConfig::Book{book, depth, engine} => {
+                let engine = engine.to_string();
+                let separator = if engine.contains(':') {','} else {':'};
+                write!(f, "{}{}book={}", engine, separator, book)?;
+                match depth {
+                    Some(depth) => write!(f, ",bookdepth={}", depth),
+                    None        => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+fn parse_param<T: FromStr>(key: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("invalid value for {}: {}", key, value))
+}
+
+impl FromStr for EngineConfig {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // the path to a weight file is case-sensitive
+        let s = s.trim();
+        let (name, params) = match s.find(':') {
+            Some(pos) => (s[..pos].to_ascii_lowercase(), &s[pos+1..]),
+            None      => (s.to_ascii_lowercase(), ""),
+        };
+        let name = name.as_str();
+        let mut book:  Option<String> = None;
+        let mut depth: Option<u32>    = None;
+        let mut config = match name {
+            "random"    => EngineConfig::Random,
+            "greedy"    => EngineConfig::Greedy{lookahead: true},
+            "naive"     => EngineConfig::Naive{time: 1},
+            "uct"       => EngineConfig::default(),
+            "alphabeta" => EngineConfig::AlphaBeta{depth: 4, time: 1},
+            "leveled"   => EngineConfig::Leveled{level: 5, time: 1},
+            "puct"      => EngineConfig::PUCT{weights: String::new(), time: 1, playouts: 800, cpuct: 1.5},
+            _ => return Err(format!("unknown engine: {}", name)),
+        };
+
+        for param in params.split(',').filter(|p| !p.is_empty()) {
+            let (key, value) = match param.find('=') {
+                Some(pos) => (param[..pos].to_ascii_lowercase(), &param[pos+1..]),
+                None      => return Err(format!("parameter needs a value: {}", param)),
+            };
+            match (&mut config, key.as_str()) {
+                (EngineConfig::PUCT{weights, ..}, "weights") => {
+                    *weights = value.to_string();
+                    continue;
+                }
+                (_, "book") => {
+                    book = Some(value.to_string());
+                    continue;
+                }
+                (_, "bookdepth") => {
+                    depth = Some(parse_param("bookdepth", value)?);
+                    continue;
+                }
+                _ => {}
+            }
+            let value = value.to_ascii_lowercase();
+            let (key, value) = (key.as_str(), value.as_str());
+            match (&mut config, key) {
+                (EngineConfig::Greedy{lookahead}, "lookahead") => *lookahead = parse_param(key, value)?,
+                (EngineConfig::Naive{time},       "time")      => *time      = parse_param(key, value)?,
+                (EngineConfig::UCT{time, ..},     "time")      => *time      = parse_param(key, value)?,
+                (EngineConfig::UCT{coeff, ..},    "coeff")     => *coeff     = parse_param(key, value)?,
+                (EngineConfig::UCT{expand, ..},   "expand")    => *expand    = parse_param(key, value)?,
+                (EngineConfig::AlphaBeta{depth, ..}, "depth")  => *depth     = parse_param(key, value)?,
+                (EngineConfig::AlphaBeta{time, ..},  "time")   => *time      = parse_param(key, value)?,
+                (EngineConfig::Leveled{level, ..},   "level")  => *level     = parse_param(key, value)?,
+                (EngineConfig::Leveled{time, ..},    "time")   => *time      = parse_param(key, value)?,
+                (EngineConfig::PUCT{time, ..},     "time")     => *time      = parse_param(key, value)?,
+                (EngineConfig::PUCT{playouts, ..}, "playouts") => *playouts  = parse_param(key, value)?,
+                (EngineConfig::PUCT{cpuct, ..},    "cpuct")    => *cpuct     = parse_param(key, value)?,
+                _ => return Err(format!("unknown parameter for {}: {}", name, key)),
+            }
+        }
+        if let EngineConfig::AlphaBeta{depth: 0, ..} = config {
+            return Err("alphabeta depth must be positive".to_string());
+        }
+        if let EngineConfig::PUCT{ref weights, ..} = config {
+            if weights.is_empty() {
+                return Err("puct needs a weight file: puct:weights=<path>".to_string());
+            }
+        }
+        match book {
+            Some(book) => Ok(EngineConfig::Book{book, depth, engine: Box::new(config)}),
+            None if depth.is_some() => Err("bookdepth needs a book".to_string()),
+            None => Ok(config),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn engine_config_roundtrip() {
+        for spec in ["random", "greedy", "naive", "uct", "alphabeta", "leveled"].iter() {
+            let config: EngineConfig = spec.parse().unwrap();
+            assert_eq!(config.to_string().parse::<EngineConfig>().unwrap(), config);
+        }
+        assert_eq!("uct:time=2,expand=5".parse::<EngineConfig>().unwrap(),
+                   EngineConfig::UCT{time: 2, coeff: 2.0, expand: 5});
+        assert_eq!("Leveled:level=3".parse::<EngineConfig>().unwrap(),
+                   EngineConfig::Leveled{level: 3, time: 1});
+
+        assert!("minimax".parse::<EngineConfig>().is_err());
+        assert!("uct:depth=3".parse::<EngineConfig>().is_err());
+        assert!("naive:time".parse::<EngineConfig>().is_err());
+        assert!("alphabeta:depth=0".parse::<EngineConfig>().is_err());
+
+        let puct: EngineConfig = "PUCT:weights=Models/9x9.bin,playouts=100".parse().unwrap();
+        assert_eq!(puct, EngineConfig::PUCT{weights: "Models/9x9.bin".to_string(),
+                                            time: 1, playouts: 100, cpuct: 1.5});
+        assert_eq!(puct.to_string().parse::<EngineConfig>().unwrap(), puct);
+        assert!("puct:playouts=100".parse::<EngineConfig>().is_err());
+
+        let book: EngineConfig = "greedy:book=Openings.bin,bookdepth=4".parse().unwrap();
+        assert_eq!(book, EngineConfig::Book{book: "Openings.bin".to_string(), depth: Some(4),
+                                            engine: Box::new(EngineConfig::Greedy{lookahead: true})});
+        assert_eq!(book.to_string(), "greedy:lookahead=true,book=Openings.bin,bookdepth=4");
+        assert_eq!("random:book=a.bin".parse::<EngineConfig>().unwrap().to_string(), "random:book=a.bin");
+        assert!("random:bookdepth=4".parse::<EngineConfig>().is_err());
+    }
+
+    #[test]
+    fn engines_play_valid_moves() {
+        let configs = ["random", "greedy", "naive:time=0", "uct:time=0",
+                       "alphabeta:depth=1", "leveled:level=2"];
+        for (i, spec) in configs.iter().enumerate() {
+            let config: EngineConfig = spec.parse().unwrap();
+            let mut engine = config.build(Color::Blue, i as u64, 5).unwrap();
+            let mut board = Board::new(5);
+            board.apply_move(board.possible_moves(Color::Red)[0], Color::Red);
+
+            let next_move = engine.genmove(&board).unwrap();
+            assert!(board.is_valid_move(next_move, Color::Blue), "{}", spec);
+        }
+
+        let path = std::env::temp_dir().join("separo-engine-test.bin");
+        std::fs::write(&path, Network::random(5, &[8], 0).to_bytes()).unwrap();
+        let config = EngineConfig::PUCT{weights: path.to_str().unwrap().to_string(),
+                                        time: 1, playouts: 20, cpuct: 1.5};
+        let mut engine = config.build(Color::Red, 0, 5).unwrap();
+        let board = Board::new(5);
+        let next_move = engine.genmove(&board).unwrap();
+        assert!(board.is_valid_move(next_move, Color::Red));
+        assert_eq!(engine.search_info().unwrap().nodes, 20);
+        assert!(config.build(Color::Red, 0, 9).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,249 @@
+// A record of a game in a PGN-like text format.
+//
+//     [Size "9"]
+//     [Red "uct:time=1,coeff=2,expand=3"]
+//     [Blue "alphabeta:depth=4,time=1"]
+//     [Seed "42"]
+//     [Score "3-2"]
+//     [Result "1-0"]
+//
+//     1. a1-b2-b3 i1-h2-h3 2. b3-c4-c5 pass ... 1-0
+//
+// Red moves first and the players alternate. A player who has no possible
+// move passes, written as `pass`. The tags other than `Size`, `Red`, `Blue`,
+// `Resigned`, `Score` and `Result` are kept as they are. `Score` and `Result`
+// are derived from the moves, so they are not stored but written for the
+// readers. `Resigned` is written only if a player resigned.
+
+use crate::{Board, Color, Move, opponent_of};
+
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameRecord {
+    pub size:     usize,
+    pub red:      String,
+    pub blue:     String,
+    pub moves:    Vec<Option<Move>>, // None means pass
+    pub resigned: Option<Color>,
+    pub tags:     Vec<(String, String)>,
+}
+
+impl GameRecord {
+    pub fn new(size: usize, red: String, blue: String) -> Self {
+        GameRecord{size, red, blue, moves: Vec::new(), resigned: None, tags: Vec::new()}
+    }
+
+    pub fn tag(&self, key: &str) -> Option<&str> {
+        self.tags.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+    }
+
+    // the player of the n-th move (0-origin)
+    pub fn turn_of(ply: usize) -> Color {
+        if ply.is_multiple_of(2) {Color::Red} else {Color::Blue}
+    }
+
+    // boards from the initial position to the final position. It fails if a
+    // move is illegal or a player passes while it can move.
+    pub fn replay(&self) -> Result<Vec<Board>, String> {
+        let mut boards = vec![Board::new(self.size)];
+        for (ply, next_move) in self.moves.iter().enumerate() {
+            let turn  = Self::turn_of(ply);
+            let mut board = boards.last().unwrap().clone();
+            match next_move {
+                Some(next_move) => {
+                    if !board.is_valid_move(*next_move, turn) {
+                        return Err(format!("illegal move at ply {}: {}", ply + 1, next_move));
+                    }
+                    board.apply_move(*next_move, turn);
+                }
+                None => {
+                    if board.can_move(turn) {
+                        return Err(format!("illegal pass at ply {}", ply + 1));
+                    }
+                }
+            }
+            boards.push(board);
+        }
+        Ok(boards)
+    }
+
+    pub fn final_board(&self) -> Result<Board, String> {
+        Ok(self.replay()?.pop().unwrap())
+    }
+
+    // scores of red and blue at the end of the game
+    pub fn scores(&self) -> Result<(u16, u16), String> {
+        let mut board = self.final_board()?;
+        Ok((board.score(Color::Red), board.score(Color::Blue)))
+    }
+
+    // None if the game is a draw
+    pub fn winner(&self) -> Result<Option<Color>, String> {
+        if let Some(color) = self.resigned {
+            return Ok(Some(opponent_of(color)));
+        }
+        let (red, blue) = self.scores()?;
+        Ok(if red > blue {
+            Some(Color::Red)
+        } else if blue > red {
+            Some(Color::Blue)
+        } else {
+            None
+        })
+    }
+
+    // read all the records in a text, e.g. a tournament archive.
+    pub fn parse_all(text: &str) -> Result<Vec<GameRecord>, String> {
+        let mut chunks: Vec<String> = Vec::new();
+        let mut in_movetext = true; // a tag after movetext starts a new record
+        for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            let is_tag = line.starts_with('[');
+            if is_tag && in_movetext {
+                chunks.push(String::new());
+            }
+            in_movetext = !is_tag;
+            match chunks.last_mut() {
+                Some(chunk) => {
+                    chunk.push_str(line);
+                    chunk.push('\n');
+                }
+                None => return Err("a record must start with tags".to_string()),
+            }
+        }
+        chunks.iter().map(|chunk| chunk.parse()).collect()
+    }
+}
+
+fn result_text(winner: Option<Color>) -> &'static str {
+    match winner {
+        Some(Color::Red)  => "1-0",
+        Some(Color::Blue) => "0-1",
+        None              => "1/2-1/2",
+    }
+}
+
+impl fmt::Display for GameRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "[Size \"{}\"]", self.size)?;
+        writeln!(f, "[Red \"{}\"]",  self.red)?;
+        writeln!(f, "[Blue \"{}\"]", self.blue)?;
+        for (key, value) in self.tags.iter() {
+            writeln!(f, "[{} \"{}\"]", key, value)?;
+        }
+        if let Some(color) = self.resigned {
+            writeln!(f, "[Resigned \"{}\"]", color)?;
+        }
+        // a broken record can be written, but without the derived tags.
+        let result = match (self.scores(), self.winner()) {
+            (Ok((red, blue)), Ok(winner)) => {
+                writeln!(f, "[Score \"{}-{}\"]", red, blue)?;
+                writeln!(f, "[Result \"{}\"]", result_text(winner))?;
+                result_text(winner)
+            }
+            _ => "*",
+        };
+        writeln!(f)?;
+
+        for (ply, next_move) in self.moves.iter().enumerate() {
+            if ply.is_multiple_of(2) {
+                write!(f, "{}. ", ply / 2 + 1)?;
+            }
+            match next_move {
+                Some(next_move) => write!(f, "{} ", next_move)?,
+                None            => write!(f, "pass ")?,
+            }
+        }
+        writeln!(f, "{}", result)
+    }
+}
+
+impl FromStr for GameRecord {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut record = GameRecord::new(0, String::new(), String::new());
+        let mut movetext = String::new();
+        for line in s.lines().map(str::trim) {
+            if !line.starts_with('[') {
+                movetext.push_str(line);
+                movetext.push(' ');
+                continue;
+            }
+            let inner = line.trim_start_matches('[').trim_end_matches(']');
+            let (key, value) = match inner.find(' ') {
+                Some(pos) => (&inner[..pos], inner[pos+1..].trim().trim_matches('"')),
+                None      => return Err(format!("invalid tag: {}", line)),
+            };
+            match key {
+                "Size"     => record.size = value.parse()
+                                  .map_err(|_| format!("invalid size: {}", value))?,
+                "Red"      => record.red  = value.to_string(),
+                "Blue"     => record.blue = value.to_string(),
+                "Resigned" => record.resigned = Some(value.parse()?),
+                "Score" | "Result" => {} // derived from the moves
+                _ => record.tags.push((key.to_string(), value.to_string())),
+            }
+        }
+        if record.size == 0 {
+            return Err("Size tag is missing".to_string());
+        }
+        if !(4..20).contains(&record.size) {
+            return Err(format!("unsupported board size: {}", record.size));
+        }
+
+        for token in movetext.split_whitespace() {
+            match token {
+                "1-0" | "0-1" | "1/2-1/2" | "*" => break,
+                "pass" => record.moves.push(None),
+                _ if token.ends_with('.') => {} // move number
+                _ => record.moves.push(Some(token.parse()?)),
+            }
+        }
+        Ok(record)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_roundtrip() {
+        let mut record = GameRecord::new(5, "greedy".to_string(), "random".to_string());
+        record.tags.push(("Seed".to_string(), "42".to_string()));
+        record.moves.push(Some("a1-b2-b3".parse().unwrap()));
+        record.moves.push(Some("a5-b4-c4".parse().unwrap()));
+        record.moves.push(Some("e5-d4-d3".parse().unwrap()));
+        record.resigned = Some(Color::Blue);
+
+        let text = record.to_string();
+        assert_eq!(text, concat!(
+            "[Size \"5\"]\n",
+            "[Red \"greedy\"]\n",
+            "[Blue \"random\"]\n",
+            "[Seed \"42\"]\n",
+            "[Resigned \"blue\"]\n",
+            "[Score \"1-1\"]\n",
+            "[Result \"1-0\"]\n",
+            "\n",
+            "1. a1-b2-b3 a5-b4-c4 2. e5-d4-d3 1-0\n"));
+        assert_eq!(text.parse::<GameRecord>().unwrap(), record);
+
+        let archive = format!("{}\n{}", text, text);
+        assert_eq!(GameRecord::parse_all(&archive).unwrap(), vec![record.clone(), record]);
+    }
+
+    #[test]
+    fn replay_rejects_illegal_moves() {
+        let mut record = GameRecord::new(5, String::new(), String::new());
+        record.moves.push(Some("a5-b4-c4".parse().unwrap())); // blue's stone
+        assert!(record.replay().is_err());
+
+        record.moves[0] = None; // red can move
+        assert!(record.replay().is_err());
+
+        record.moves[0] = Some("a1-b2-b3".parse().unwrap());
+        assert_eq!(record.replay().unwrap().len(), 2);
+    }
+}
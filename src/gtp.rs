@@ -0,0 +1,402 @@
+// A text protocol modeled after GTP (Go Text Protocol, version 2) so that the
+// engines can be driven by a match runner or by hand in a terminal.
+//
+// Each line is a command, optionally preceded by a numeric id. A response
+// starts with `=` (success) or `?` (failure), followed by the id and the
+// result, and ends with an empty line.
+//
+//     play red c1-d2-e2     # or `play black ...`. red moves first.
+//     =
+//
+//     genmove blue
+//     = e5-d4-c4
+//
+// A move is written as three grids joined by `-` (see `Move`'s Display).
+// `pass` is accepted only if the player has no possible move, and `genmove`
+// returns `pass` or `resign` if the engine does not play.
+
+use crate::{Board, Color, Move};
+use crate::engine::{Engine, EngineConfig};
+
+use std::io::{self, BufRead, Write};
+
+const COMMANDS: [&str; 15] = [
+    "protocol_version",
+    "name",
+    "version",
+    "known_command",
+    "list_commands",
+    "quit",
+    "boardsize",
+    "clear_board",
+    "play",
+    "genmove",
+    "undo",
+    "showboard",
+    "final_score",
+    "time_settings",
+    "engine",
+];
+
+const MIN_BOARD_SIZE: usize = 4;
+const MAX_BOARD_SIZE: usize = 19;
+
+pub struct Gtp {
+    config:  EngineConfig,
+    seed:    u64,
+    board:   Board,
+    history: Vec<Board>,
+    engines: Vec<Box<dyn Engine>>,
+    quit:    bool,
+}
+
+// In GTP, colors are black (moves first) and white. Accept them as well.
+fn parse_color(s: &str) -> Result<Color, String> {
+    match s.to_ascii_lowercase().as_str() {
+        "black" | "b" => Ok(Color::Red),
+        "white" | "w" => Ok(Color::Blue),
+        _             => s.parse(),
+    }
+}
+
+impl Gtp {
+    pub fn new(config: EngineConfig, seed: u64) -> Self {
+        Gtp{
+            config,
+            seed,
+            board:   Board::new(9),
+            history: Vec::new(),
+            engines: Vec::new(),
+            quit:    false,
+        }
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    // run commands until `quit` or EOF.
+    pub fn run<R: BufRead, W: Write>(&mut self, input: R, mut output: W) -> io::Result<()> {
+        for line in input.lines() {
+            if let Some(response) = self.execute(&line?) {
+                output.write_all(response.as_bytes())?;
+                output.flush()?;
+            }
+            if self.quit {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    // execute a command line and return the response. Empty lines and
+    // comments are ignored.
+    pub fn execute(&mut self, line: &str) -> Option<String> {
+        let line = match line.find('#') {
+            Some(pos) => &line[..pos],
+            None      => line,
+        };
+        let mut args = line.split_whitespace().collect::<Vec<_>>();
+        if args.is_empty() {
+            return None;
+        }
+        let id = if args[0].bytes().all(|b| b.is_ascii_digit()) {
+            args.remove(0)
+        } else {
+            ""
+        };
+        if args.is_empty() {
+            return Some(format!("?{} empty command\n\n", id));
+        }
+
+        let command = args[0].to_ascii_lowercase();
+        Some(match self.dispatch(&command, &args[1..]) {
+            Ok(result) => format!("={} {}\n\n", id, result),
+            Err(err)   => format!("?{} {}\n\n", id, err),
+        })
+    }
+
+    fn dispatch(&mut self, command: &str, args: &[&str]) -> Result<String, String> {
+        match command {
+            "protocol_version" => Ok("2".to_string()),
+            "name"             => Ok(env!("CARGO_PKG_NAME").to_string()),
+            "version"          => Ok(env!("CARGO_PKG_VERSION").to_string()),
+            "known_command"    => {
+                let known = args.first().is_some_and(|c| COMMANDS.contains(c));
+                Ok(known.to_string())
+            }
+            "list_commands" => Ok(COMMANDS.join("\n")),
+            "quit" => {
+                self.quit = true;
+                Ok(String::new())
+            }
+            "boardsize"     => self.boardsize(args),
+            "clear_board"   => {
+                self.reset(self.board.width as usize);
+                Ok(String::new())
+            }
+            "play"          => self.play(args),
+            "genmove"       => self.genmove(args),
+            "undo"          => {
+                self.board = self.history.pop().ok_or_else(|| "cannot undo".to_string())?;
+                Ok(String::new())
+            }
+            "showboard"     => Ok(format!("\n{}", self.showboard())),
+            "final_score"   => Ok(self.final_score()),
+            "time_settings" => self.time_settings(args),
+            "engine"        => {
+                if let Some(spec) = args.first() {
+                    self.config  = spec.parse()?;
+                    self.engines.clear();
+                }
+                Ok(self.config.to_string())
+            }
+            _ => Err("unknown command".to_string()),
+        }
+    }
+
+    fn reset(&mut self, width: usize) {
+        self.board = Board::new(width);
+        self.history.clear();
+        self.engines.clear();
+    }
+
+    fn boardsize(&mut self, args: &[&str]) -> Result<String, String> {
+        let width: usize = args.first().and_then(|s| s.parse().ok())
+            .ok_or_else(|| "boardsize needs a number".to_string())?;
+        if !(MIN_BOARD_SIZE..=MAX_BOARD_SIZE).contains(&width) {
+            return Err("unacceptable size".to_string());
+        }
+        self.reset(width);
+        Ok(String::new())
+    }
+
+    fn play(&mut self, args: &[&str]) -> Result<String, String> {
+        if args.len() != 2 {
+            return Err("play needs a color and a move".to_string());
+        }
+        let color = parse_color(args[0])?;
+        if args[1].eq_ignore_ascii_case("pass") {
+            if self.board.can_move(color) {
+                return Err("illegal move".to_string());
+            }
+            self.history.push(self.board.clone());
+            return Ok(String::new());
+        }
+        let next_move: Move = args[1].parse()?;
+        if !self.board.is_valid_move(next_move, color) {
+            return Err("illegal move".to_string());
+        }
+        self.history.push(self.board.clone());
+        self.board.apply_move(next_move, color);
+        Ok(String::new())
+    }
+
+    fn genmove(&mut self, args: &[&str]) -> Result<String, String> {
+        let color = parse_color(args.first().ok_or_else(|| "genmove needs a color".to_string())?)?;
+
+        // engines are kept while the game continues, so that they can re-use
+        // their search tree.
+        let idx = match self.engines.iter().position(|e| e.color() == color) {
+            Some(idx) => idx,
+            None => {
+                let seed = self.seed.wrapping_add(color as u64);
+                self.engines.push(self.config.build(color, seed, self.board.width as usize)?);
+                self.engines.len() - 1
+            }
+        };
+        let engine = &mut self.engines[idx];
+        let next_move = engine.genmove(&self.board);
+        if engine.has_resigned() {
+            return Ok("resign".to_string());
+        }
+
+        self.history.push(self.board.clone());
+        match next_move {
+            Some(next_move) => {
+                assert!(self.board.is_valid_move(next_move, color));
+                self.board.apply_move(next_move, color);
+                Ok(next_move.to_string())
+            }
+            None => Ok("pass".to_string()),
+        }
+    }
+
+    // GTP does not specify the format of the board. Stones are drawn as `R`
+    // and `B`, and roots between them as `-`, `|`, `\` and `/`.
+    fn showboard(&mut self) -> String {
+        let width = self.board.width as usize;
+        let size  = 2 * width - 1;
+        let mut canvas = vec![vec![' '; size]; size];
+        for x in 0..width {
+            for y in 0..width {
+                let grid = &self.board.grids[x * width + y];
+                canvas[2*y][2*x] = match grid.color {
+                    Some(Color::Red)  => 'R',
+                    Some(Color::Blue) => 'B',
+                    None              => '.',
+                };
+                for dir in grid.roots.iter() {
+                    let cx = (2 * x as i32 + dir.0 as i32) as usize;
+                    let cy = (2 * y as i32 + dir.1 as i32) as usize;
+                    let c = match (dir.0, dir.1) {
+                        (0, _) => '|',
+                        (_, 0) => '-',
+                        (dx, dy) if dx == dy => '\\',
+                        _ => '/',
+                    };
+                    // two diagonal roots of different colors can cross.
+                    canvas[cy][cx] = if canvas[cy][cx] != ' ' && canvas[cy][cx] != c {'X'} else {c};
+                }
+            }
+        }
+
+        let columns = (0..width).map(|x| ((b'a' + x as u8) as char).to_string())
+            .collect::<Vec<_>>();
+        let mut text = format!("   {}\n", columns.join(" "));
+        for (row, line) in canvas.iter().enumerate() {
+            let label = if row.is_multiple_of(2) {format!("{:>2}", row / 2 + 1)} else {String::new()};
+            let line  = format!("{:2} {}", label, line.iter().collect::<String>());
+            text += line.trim_end();
+            text.push('\n');
+        }
+        text += &format!("red: {}, blue: {}",
+                         self.board.score(Color::Red), self.board.score(Color::Blue));
+        text
+    }
+
+    fn final_score(&mut self) -> String {
+        let red  = self.board.score(Color::Red)  as i32;
+        let blue = self.board.score(Color::Blue) as i32;
+        if red > blue {
+            format!("R+{}", red - blue)
+        } else if blue > red {
+            format!("B+{}", blue - red)
+        } else {
+            "0".to_string()
+        }
+    }
+
+    // time_settings main_time byo_yomi_time byo_yomi_stones
+    // The engines have a fixed time limit per move, so it is set to the
+    // byo-yomi time per stone, or a fraction of the main time if no byo-yomi.
+    fn time_settings(&mut self, args: &[&str]) -> Result<String, String> {
+        let values = args.iter().map(|s| s.parse::<u32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| "time_settings needs 3 numbers".to_string())?;
+        if values.len() != 3 {
+            return Err("time_settings needs 3 numbers".to_string());
+        }
+        let (main_time, byo_yomi_time, byo_yomi_stones) = (values[0], values[1], values[2]);
+        let per_move = if byo_yomi_time > 0 && byo_yomi_stones > 0 {
+            byo_yomi_time / byo_yomi_stones
+        } else {
+            main_time / 30
+        };
+        if main_time == 0 && byo_yomi_time == 0 {
+            return Ok(String::new()); // no time limit. keep the default.
+        }
+        self.config.set_time_limit(u32::max(per_move, 1));
+        self.engines.clear();
+        Ok(String::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_commands(gtp: &mut Gtp, commands: &str) -> String {
+        let mut output = Vec::new();
+        gtp.run(commands.as_bytes(), &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn play_and_undo() {
+        let mut gtp = Gtp::new(EngineConfig::Random, 42);
+        let output = run_commands(&mut gtp,
+            "boardsize 5\n1 play black a1-b2-c2\nplay red a1-b2-b3\nundo\nundo\n");
+        assert_eq!(output, "= \n\n=1 \n\n? illegal move\n\n= \n\n? cannot undo\n\n");
+        assert_eq!(gtp.board(), &Board::new(5));
+
+        assert_eq!(gtp.execute("play blue a1-b2-c2"), Some("? illegal move\n\n".to_string()));
+        assert_eq!(gtp.execute("play red a1"), Some("? a move needs 3 grids: a1\n\n".to_string()));
+        assert_eq!(gtp.execute("play red pass"), Some("? illegal move\n\n".to_string()));
+        assert_eq!(gtp.execute("# comment only"), None);
+        assert_eq!(gtp.execute("7 known_command genmove"), Some("=7 true\n\n".to_string()));
+        assert_eq!(gtp.execute("frobnicate"), Some("? unknown command\n\n".to_string()));
+    }
+
+    #[test]
+    fn engines_play_a_game() {
+        let mut gtp = Gtp::new("greedy".parse().unwrap(), 42);
+        gtp.execute("boardsize 5");
+        let mut passes = 0;
+        for color in ["red", "blue"].iter().cycle() {
+            let response = gtp.execute(&format!("genmove {}", color)).unwrap();
+            assert!(response.starts_with("= "), "{}", response);
+            if response == "= pass\n\n" {
+                passes += 1;
+                if passes == 2 {
+                    break;
+                }
+            } else {
+                passes = 0;
+            }
+        }
+        assert!(gtp.board().is_gameover());
+
+        let final_score = gtp.execute("final_score").unwrap();
+        let mut board = gtp.board().clone();
+        let red  = board.score(Color::Red)  as i32;
+        let blue = board.score(Color::Blue) as i32;
+        let expected = match red - blue {
+            d if d > 0 => format!("= R+{}\n\n", d),
+            d if d < 0 => format!("= B+{}\n\n", -d),
+            _          => "= 0\n\n".to_string(),
+        };
+        assert_eq!(final_score, expected);
+    }
+
+    #[test]
+    fn showboard() {
+        let mut gtp = Gtp::new(EngineConfig::Random, 42);
+        gtp.execute("boardsize 4");
+        gtp.execute("play red a1-b2-b3");
+        assert_eq!(gtp.execute("showboard").unwrap(), concat!(
+            "= \n",
+            "   a b c d\n",
+            " 1 R . . B\n",
+            "    \\\n",
+            " 2 . R . .\n",
+            "     |\n",
+            " 3 . R . .\n",
+            "\n",
+            " 4 B . . R\n",
+            "red: 1, blue: 1\n\n"));
+    }
+
+    #[test]
+    fn time_settings() {
+        let mut gtp = Gtp::new("uct".parse().unwrap(), 42);
+        assert_eq!(gtp.execute("time_settings 0 10 5"), Some("= \n\n".to_string()));
+        assert_eq!(gtp.execute("engine"), Some("= uct:time=2,coeff=2,expand=3\n\n".to_string()));
+        gtp.execute("time_settings 300 0 0");
+        assert_eq!(gtp.execute("engine"), Some("= uct:time=10,coeff=2,expand=3\n\n".to_string()));
+        assert!(gtp.execute("time_settings 1 2").unwrap().starts_with('?'));
+    }
+
+    #[test]
+    fn network_for_another_size() {
+        let path = std::env::temp_dir().join("separo-gtp-test.bin");
+        std::fs::write(&path, crate::network::Network::random(5, &[8], 0).to_bytes()).unwrap();
+        let mut gtp = Gtp::new(format!("puct:weights={},playouts=10", path.to_str().unwrap())
+                                   .parse().unwrap(), 42);
+        gtp.execute("boardsize 9");
+        assert!(gtp.execute("genmove red").unwrap().starts_with("? "));
+        gtp.execute("boardsize 5");
+        assert!(gtp.execute("genmove red").unwrap().starts_with("= "));
+        std::fs::remove_file(path).unwrap();
+    }
+}
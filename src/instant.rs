@@ -14,8 +14,8 @@ pub use std::time::*;
     pub fn now() -> Self { Self(std::time::Instant::now()) }
     pub fn duration_since(&self, earlier: Instant) -> Duration { self.0.duration_since(earlier.0) }
     pub fn elapsed(&self) -> Duration { self.0.elapsed() }
-    pub fn checked_add(&self, duration: Duration) -> Option<Self> { self.0.checked_add(duration).map(|i| Self(i)) }
-    pub fn checked_sub(&self, duration: Duration) -> Option<Self> { self.0.checked_sub(duration).map(|i| Self(i)) }
+    pub fn checked_add(&self, duration: Duration) -> Option<Self> { self.0.checked_add(duration).map(Self) }
+    pub fn checked_sub(&self, duration: Duration) -> Option<Self> { self.0.checked_sub(duration).map(Self) }
 }
 
 #[cfg(target_arch = "wasm32")] #[wasm_bindgen] extern "C" { #[wasm_bindgen(js_namespace = Date, js_name = now)] fn date_now() -> f64; }
@@ -26,13 +26,13 @@ pub use std::time::*;
     pub fn elapsed(&self) -> Duration { Self::now().duration_since(*self) }
     pub fn checked_add(&self, duration: Duration) -> Option<Self> {
         match duration.as_millis().try_into() {
-            Ok(duration) => self.0.checked_add(duration).map(|i| Self(i)),
+            Ok(duration) => self.0.checked_add(duration).map(Self),
             Err(_) => None,
         }
     }
     pub fn checked_sub(&self, duration: Duration) -> Option<Self> {
         match duration.as_millis().try_into() {
-            Ok(duration) => self.0.checked_sub(duration).map(|i| Self(i)),
+            Ok(duration) => self.0.checked_sub(duration).map(Self),
             Err(_) => None,
         }
     }
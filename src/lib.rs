@@ -8,6 +8,9 @@ use serde_repr::Serialize_repr;
 use std::vec::Vec;
 use std::option::Option;
 use std::time::Duration;
+use std::fmt;
+use std::str::FromStr;
+use std::collections::{BTreeMap, BTreeSet};
 
 use std::rc::{Rc, Weak};
 use std::cell::{RefCell, RefMut};
@@ -15,7 +18,25 @@ use std::cell::{RefCell, RefMut};
 mod instant;
 use crate::instant::Instant;
 
-mod recorder;
+use crate::engine::SearchInfo;
+
+pub mod apng;
+pub mod recorder;
+pub mod render;
+pub mod svg;
+
+pub mod book;
+pub mod database;
+pub mod engine;
+pub mod game_record;
+pub mod gtp;
+pub mod network;
+pub mod puct;
+pub mod selfplay;
+pub mod sprt;
+pub mod tournament;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod server;
 
 // When the `wee_alloc` feature is enabled, this uses `wee_alloc` as the global
 // allocator.
@@ -27,12 +48,24 @@ static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 
 // XXX: Here we assume that the board is always square.
 
+#[cfg(target_arch = "wasm32")]
 macro_rules! console_log {
     ($($arg:tt)*) => {
         web_sys::console::log_1(&format!( $($arg)* ).into())
     }
 }
 
+// web_sys::console is not available outside of the browser. In native debug
+// builds (e.g. `cargo test`), print the log to stderr instead.
+#[cfg(not(target_arch = "wasm32"))]
+macro_rules! console_log {
+    ($($arg:tt)*) => {
+        if cfg!(debug_assertions) {
+            eprintln!( $($arg)* )
+        }
+    }
+}
+
 // Grid position. left-top: (0,0), right-bottom: (N,N).
 // We will never use 256x256 board. The max size would be 19x19. u8 is enough.
 #[wasm_bindgen]
@@ -48,10 +81,104 @@ impl Coord {
     }
 }
 
+// In text, a grid is written as a column letter and a row number, like `c4`.
+// The left-top grid (0,0) is `a1`. Unlike Go, `i` is not skipped.
+impl fmt::Display for Coord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", (b'a' + self.x as u8) as char, self.y + 1)
+    }
+}
+
+impl FromStr for Coord {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_ascii_lowercase();
+        let column = s.bytes().next().ok_or_else(|| "empty coordinate".to_string())?;
+        if !column.is_ascii_lowercase() {
+            return Err(format!("invalid coordinate: {}", s));
+        }
+        let row: i8 = s[1..].parse().map_err(|_| format!("invalid coordinate: {}", s))?;
+        if row < 1 {
+            return Err(format!("invalid coordinate: {}", s));
+        }
+        Ok(Coord::new((column - b'a') as i8, row - 1))
+    }
+}
+
 #[wasm_bindgen]
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize)]
 pub struct Move (Coord, Coord, Coord);
 
+// A move is written as three grids joined by `-`, like `a1-b2-b3`.
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}-{}-{}", self.0, self.1, self.2)
+    }
+}
+
+impl FromStr for Move {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let stones = s.split('-').map(Coord::from_str).collect::<Result<Vec<_>, _>>()?;
+        if stones.len() != 3 {
+            return Err(format!("a move needs 3 grids: {}", s));
+        }
+        Ok(Move(stones[0], stones[1], stones[2]))
+    }
+}
+
+// The first step of a move is diagonal and the second step is horizontal or
+// vertical, so a move is identified by the first stone, the direction of the
+// first step (4) and whether the second step is horizontal or vertical (2).
+// This gives a stable index of a move in a policy vector of size
+// `width * width * 8`, regardless of the board state.
+const FIRST_STEPS: [Dir; 4] = [Dir(1,1), Dir(-1,1), Dir(-1,-1), Dir(1,-1)];
+
+pub fn policy_size(width: usize) -> usize {
+    width * width * FIRST_STEPS.len() * 2
+}
+
+impl Move {
+    // None if the stones do not form a move on the board.
+    pub fn policy_index(&self, width: usize) -> Option<usize> {
+        let Move(stone1, stone2, stone3) = *self;
+        let step1 = Dir(stone2.x - stone1.x, stone2.y - stone1.y);
+        let step2 = Dir(stone3.x - stone2.x, stone3.y - stone2.y);
+        let first = FIRST_STEPS.iter().position(|d| *d == step1)?;
+        let second = if step2 == Dir(step1.0, 0) {
+            0
+        } else if step2 == Dir(0, step1.1) {
+            1
+        } else {
+            return None;
+        };
+        let on_board = |c: Coord| 0 <= c.x && 0 <= c.y && (c.x as usize) < width && (c.y as usize) < width;
+        if !on_board(stone1) || !on_board(stone3) {
+            return None;
+        }
+        let grid = stone1.x as usize * width + stone1.y as usize;
+        Some((grid * FIRST_STEPS.len() + first) * 2 + second)
+    }
+
+    // the inverse of `policy_index`. None if the move goes out of the board.
+    pub fn from_policy_index(index: usize, width: usize) -> Option<Move> {
+        if policy_size(width) <= index {
+            return None;
+        }
+        let second = index % 2;
+        let first  = index / 2 % FIRST_STEPS.len();
+        let grid   = index / 2 / FIRST_STEPS.len();
+        let step1  = FIRST_STEPS[first];
+        let step2  = if second == 0 {Dir(step1.0, 0)} else {Dir(0, step1.1)};
+
+        let stone1 = Coord::new((grid / width) as i8, (grid % width) as i8);
+        let stone2 = Coord::new(stone1.x + step1.0, stone1.y + step1.1);
+        let stone3 = Coord::new(stone2.x + step2.0, stone2.y + step2.1);
+        let next_move = Move(stone1, stone2, stone3);
+        next_move.policy_index(width).map(|_| next_move)
+    }
+}
+
 #[wasm_bindgen]
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize_repr)]
 #[repr(u8)]
@@ -60,6 +187,26 @@ pub enum Color {
     Blue = 1,
 }
 
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Color::Red  => write!(f, "red"),
+            Color::Blue => write!(f, "blue"),
+        }
+    }
+}
+
+impl FromStr for Color {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "red"  => Ok(Color::Red),
+            "blue" => Ok(Color::Blue),
+            _      => Err(format!("invalid color: {}", s)),
+        }
+    }
+}
+
 fn opponent_of(color: Color) -> Color {
     match color {
         Color::Red => Color::Blue,
@@ -102,6 +249,81 @@ impl Node {
         Node{region: None, edges: ArrayVec::new()}
     }
 }
+// A connected component of a graph. The cell (x, y) is the square between the
+// grids (x, y) and (x+1, y+1), and is divided into 4 triangles by its
+// diagonals, one per node (N, E, S, W). A region scores if it has more than 4
+// nodes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Region {
+    pub nodes:  u16,
+    pub scores: bool,
+    pub cells:  Vec<RegionCell>, // in the order of x, then y
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct RegionCell {
+    pub x:     i8,
+    pub y:     i8,
+    pub parts: [bool; 4], // the triangles N, E, S, W in the region
+}
+
+impl Region {
+    // The boundary of the region as polygons in board coordinates, where the
+    // grid (x, y) is the point (x, y). The triangles of the region are merged
+    // and the points on a straight edge are removed. With the y axis down, an
+    // outer boundary goes clockwise and the boundary of a hole goes
+    // counter-clockwise, so the polygons can be filled with the nonzero rule.
+    pub fn outline(&self) -> Vec<Vec<(f64, f64)>> {
+        // in half units, so that the centers of cells are integers
+        type Point = (i16, i16);
+        let mut edges: BTreeSet<(Point, Point)> = BTreeSet::new();
+        for cell in self.cells.iter() {
+            let (x, y) = (cell.x as i16 * 2, cell.y as i16 * 2);
+            let corners = [(x, y), (x + 2, y), (x + 2, y + 2), (x, y + 2)];
+            let center  = (x + 1, y + 1);
+            for (part, _) in cell.parts.iter().enumerate().filter(|(_, p)| **p) {
+                let (a, b) = (corners[part], corners[(part + 1) % 4]);
+                // an edge shared by two triangles appears in both directions
+                for edge in [(a, b), (b, center), (center, a)].iter() {
+                    if !edges.remove(&(edge.1, edge.0)) {
+                        edges.insert(*edge);
+                    }
+                }
+            }
+        }
+
+        let mut next: BTreeMap<Point, Vec<Point>> = BTreeMap::new();
+        for (a, b) in edges.iter() {
+            next.entry(*a).or_default().push(*b);
+        }
+        let mut polygons = Vec::new();
+        while let Some(start) = next.keys().next().cloned() {
+            let mut points = vec![start];
+            let mut current = start;
+            loop {
+                let nexts = next.get_mut(&current).unwrap();
+                let to = nexts.pop().unwrap();
+                if nexts.is_empty() {
+                    next.remove(&current);
+                }
+                if to == start {
+                    break; // another loop may also touch the start point
+                }
+                points.push(to);
+                current = to;
+            }
+            // remove the points in the middle of straight lines
+            let n = points.len();
+            let polygon = (0..n).filter(|i| {
+                let (p, c, q) = (points[(i + n - 1) % n], points[*i], points[(i + 1) % n]);
+                (c.0 - p.0) * (q.1 - c.1) != (c.1 - p.1) * (q.0 - c.0)
+            }).map(|i| (points[i].0 as f64 / 2.0, points[i].1 as f64 / 2.0)).collect();
+            polygons.push(polygon);
+        }
+        polygons
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 struct Graph {
     ngrids: u8,     // width of the board (# of lines) - 1
@@ -187,7 +409,7 @@ impl Graph {
                 self.remove_edge(stone2, NodePos::S, stone2, NodePos::E);
             }
             _ => {
-                assert!(false);
+                unreachable!();
             }
         }
         // second root
@@ -221,7 +443,7 @@ impl Graph {
                 }
             }
             _ => {
-                assert!(false);
+                unreachable!();
             }
         }
     }
@@ -234,7 +456,7 @@ impl Graph {
 
         let mut score: u16 = 0;
         let mut region:u16 = 0;
-        while let Some(idx) = self.nodes.iter().position(|n| n.region == None) {
+        while let Some(idx) = self.nodes.iter().position(|n| n.region.is_none()) {
             let pos = match idx % 4 {
                 0 => NodePos::N,
                 1 => NodePos::E,
@@ -255,6 +477,70 @@ impl Graph {
         score
     }
 
+    // cut the edges that intersect with a root. Unlike `apply_move`, it does
+    // nothing if the edges are already removed.
+    fn cut(&mut self, from: Coord, to: Coord) {
+        let (x, y) = (i8::min(from.x, to.x), i8::min(from.y, to.y));
+        let crd = Coord::new(x, y);
+        let upper = self.ngrids as i8;
+        let mut remove = |crd1: Coord, pos1: NodePos, crd2: Coord, pos2: NodePos| {
+            self.at_mut(crd1, pos1).edges.retain(|e| *e != (crd2, pos2));
+            self.at_mut(crd2, pos2).edges.retain(|e| *e != (crd1, pos1));
+        };
+        if from.x != to.x && from.y != to.y {
+            if (to.x - from.x) == (to.y - from.y) { // left-top to right-bottom
+                remove(crd, NodePos::N, crd, NodePos::W);
+                remove(crd, NodePos::S, crd, NodePos::E);
+            } else { // right-top to left-bottom
+                remove(crd, NodePos::N, crd, NodePos::E);
+                remove(crd, NodePos::S, crd, NodePos::W);
+            }
+        } else if from.y == to.y { // horizontal
+            if x < upper && 1 <= y && y < upper {
+                remove(Coord::new(x, y-1), NodePos::S, crd, NodePos::N);
+            }
+        } else if 1 <= x && x < upper && y < upper { // vertical
+            remove(Coord::new(x-1, y), NodePos::E, crd, NodePos::W);
+        }
+    }
+
+    // assign region IDs and returns the number of nodes in each region
+    fn region_sizes(&mut self) -> Vec<u16> {
+        self.score();
+        let mut sizes = Vec::new();
+        for node in self.nodes.iter() {
+            let region = node.region.unwrap() as usize;
+            if sizes.len() <= region {
+                sizes.resize(region + 1, 0);
+            }
+            sizes[region] += 1;
+        }
+        sizes
+    }
+
+    // regions in the order of their IDs
+    fn regions(&mut self) -> Vec<Region> {
+        let mut regions = self.region_sizes().into_iter()
+            .map(|nodes| Region{nodes, scores: 4 < nodes, cells: Vec::new()})
+            .collect::<Vec<_>>();
+        let ngrids = self.ngrids as usize;
+        for (idx, node) in self.nodes.iter().enumerate() {
+            let region = &mut regions[node.region.unwrap() as usize];
+            let cell = idx / 4;
+            let (x, y) = ((cell / ngrids) as i8, (cell % ngrids) as i8);
+            // the nodes of a cell are next to each other
+            match region.cells.last_mut() {
+                Some(c) if (c.x, c.y) == (x, y) => c.parts[idx % 4] = true,
+                _ => {
+                    let mut parts = [false; 4];
+                    parts[idx % 4] = true;
+                    region.cells.push(RegionCell{x, y, parts});
+                }
+            }
+        }
+        regions
+    }
+
     fn find_connected_component(&mut self, region: u16, crd: Coord, pos: NodePos) -> u16 {
         let mut num: u16 = 0;
         let mut queue = Vec::new();
@@ -264,7 +550,7 @@ impl Graph {
             num += 1;
             self.at_mut(crd, pos).region = Some(region);
             for (n_crd, n_pos) in self.at(crd, pos).edges.iter() {
-                if self.at(*n_crd, *n_pos).region == None {
+                if self.at(*n_crd, *n_pos).region.is_none() {
                     queue.push((*n_crd, *n_pos));
                 }
             }
@@ -341,6 +627,30 @@ impl Grid {
 }
 
 
+// the number of the feature planes returned by `Board::to_planes`
+pub const NUM_PLANES: usize = 28;
+
+// directions of the roots in the feature planes, counterclockwise from +x
+const ROOT_DIRS: [Dir; 8] = [Dir( 1, 0), Dir( 1, 1), Dir(0,  1), Dir(-1,  1),
+                             Dir(-1, 0), Dir(-1,-1), Dir(0, -1), Dir( 1, -1)];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+struct Stone {
+    x: i8,
+    y: i8,
+    color: i8,
+}
+
+// a root seen from one end. A root appears twice, once from each end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+struct Root {
+    x1: i8,
+    y1: i8,
+    x2: i8,
+    y2: i8,
+    color: i8,
+}
+
 #[wasm_bindgen]
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Board {
@@ -418,7 +728,7 @@ impl Board {
 
                         if !(x3 < 0 || y3 < 0 || self.width as i8 <= x3 || self.width as i8 <= y3) {
                             let idx3 = x3 as usize * self.width as usize + y3 as usize;
-                            if (self.grids[idx3].color == None || self.grids[idx3].color == Some(turn)) &&
+                            if (self.grids[idx3].color.is_none() || self.grids[idx3].color == Some(turn)) &&
                                 self.grids[idx3].is_valid_root(Dir(-dir2.0, -dir2.1)) {
                                 moves.push(Move(Coord::new(x1, y1), Coord::new(x2, y2), Coord::new(x3, y3)));
                             }
@@ -436,7 +746,7 @@ impl Board {
 
                         if !(x3 < 0 || y3 < 0 || self.width as i8 <= x3 || self.width as i8 <= y3) {
                             let idx3 = x3 as usize * self.width as usize + y3 as usize;
-                            if (self.grids[idx3].color == None || self.grids[idx3].color == Some(turn)) &&
+                            if (self.grids[idx3].color.is_none() || self.grids[idx3].color == Some(turn)) &&
                                 self.grids[idx3].is_valid_root(Dir(-dir2.0, -dir2.1)) {
                                 moves.push(Move(Coord::new(x1, y1), Coord::new(x2, y2), Coord::new(x3, y3)));
                             }
@@ -448,6 +758,7 @@ impl Board {
         moves
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn apply_move_if_possible(&mut self,
         x1: i32, y1: i32, x2: i32, y2: i32, x3: i32, y3: i32, color: Color
         ) -> bool {
@@ -471,6 +782,98 @@ impl Board {
         self.possible_moves(Color::Blue).is_empty()
     }
 
+    // The outcome is decided if no move can change the scores any more.
+    pub fn is_decided(&self) -> bool {
+        [Color::Red, Color::Blue].iter()
+            .all(|turn| !self.can_move(*turn) || self.is_score_fixed(*turn))
+    }
+
+    // To check if the score of a player can change, we first cut all the edges
+    // that the player may cut in the future (see `future_roots`). After that,
+    // each region is split into pieces. The score of a region never changes
+    // if the region has 4 or less nodes, or if the region has one piece with
+    // more than 4 nodes and the rest has 4 or less nodes in total. In the
+    // latter case, any part separated from the largest piece has 4 or less
+    // nodes, so it does not count.
+    fn is_score_fixed(&self, turn: Color) -> bool {
+        let mut graph = match turn {
+            Color::Red  => self.red.clone(),
+            Color::Blue => self.blue.clone(),
+        };
+        let regions = graph.region_sizes();
+
+        let mut pieces = graph.clone();
+        for (from, to) in self.future_roots(turn) {
+            pieces.cut(from, to);
+        }
+        let piece_sizes = pieces.region_sizes();
+
+        // the largest piece in each region
+        let mut largest = vec![0; regions.len()];
+        for (node, piece) in graph.nodes.iter().zip(pieces.nodes.iter()) {
+            let region = node.region.unwrap() as usize;
+            let size   = piece_sizes[piece.region.unwrap() as usize];
+            largest[region] = u16::max(largest[region], size);
+        }
+        regions.iter().zip(largest.iter())
+            .all(|(region, piece)| *region <= 4 || (4 < *piece && region - piece <= 4))
+    }
+
+    // List up roots that the player may put in the future, by following the
+    // moves from the current stones and the stones that may be put later.
+    // Roots from the current stones must not collide with the existing roots.
+    // For simplicity, the stones that may be put later are considered to have
+    // no roots and the empty grids are considered to be kept empty. So the
+    // list may contain roots that the player actually cannot put. The same
+    // root may appear more than once.
+    fn future_roots(&self, turn: Color) -> Vec<(Coord, Coord)> {
+        let width = self.width as i8;
+        let index = |crd: Coord| crd.x as usize * self.width as usize + crd.y as usize;
+        let is_on_board = |crd: Coord| 0 <= crd.x && 0 <= crd.y && crd.x < width && crd.y < width;
+
+        // grids that have (or may have) a stone of the player
+        let mut stones = self.grids.iter()
+            .map(|g| g.color == Some(turn)).collect::<Vec<_>>();
+        let mut queue = (0..self.grids.len())
+            .filter(|idx| stones[*idx])
+            .map(|idx| Coord::new((idx / self.width as usize) as i8, (idx % self.width as usize) as i8))
+            .collect::<Vec<_>>();
+
+        let mut roots = Vec::new();
+        while let Some(stone1) = queue.pop() {
+            let grid1 = &self.grids[index(stone1)];
+            for dir1 in [Dir(1,1), Dir(-1,1), Dir(-1,-1), Dir(1,-1)].iter() {
+                let stone2 = Coord::new(stone1.x + dir1.0, stone1.y + dir1.1);
+                if !is_on_board(stone2) || self.grids[index(stone2)].color.is_some() ||
+                   (grid1.color == Some(turn) && !grid1.is_valid_root(*dir1)) {
+                    continue;
+                }
+                roots.push((stone1, stone2));
+
+                for dir2 in [Dir(dir1.0, 0), Dir(0, dir1.1)].iter() {
+                    let stone3 = Coord::new(stone2.x + dir2.0, stone2.y + dir2.1);
+                    if !is_on_board(stone3) {
+                        continue;
+                    }
+                    let grid3 = &self.grids[index(stone3)];
+                    if grid3.color.is_none() ||
+                       (grid3.color == Some(turn) && grid3.is_valid_root(Dir(-dir2.0, -dir2.1))) {
+                        roots.push((stone2, stone3));
+                    }
+                    if grid3.color.is_none() && !stones[index(stone3)] {
+                        stones[index(stone3)] = true;
+                        queue.push(stone3);
+                    }
+                }
+                if !stones[index(stone2)] {
+                    stones[index(stone2)] = true;
+                    queue.push(stone2);
+                }
+            }
+        }
+        roots
+    }
+
     fn is_valid_move(&self, next_move: Move, turn: Color) -> bool {
         self.possible_moves(turn).contains(&next_move)
     }
@@ -501,6 +904,11 @@ impl Board {
         }
     }
 
+    // Each move adds 4 roots (2 roots x 2 ends) to the grids.
+    pub fn num_moves(&self) -> usize {
+        self.grids.iter().map(|g| g.roots.len()).sum::<usize>() / 4
+    }
+
     pub fn score(&mut self, color: Color) -> u16 {
         match color {
             Color::Red  => {self.red .score()}
@@ -509,50 +917,13 @@ impl Board {
     }
 
     pub fn to_json(&self) -> String {
-        #[derive(Serialize)]
-        struct Stone {
-            x: i8,
-            y: i8,
-            color: i8,
-        }
-
-        #[derive(Serialize)]
-        struct Root {
-            x1: i8,
-            y1: i8,
-            x2: i8,
-            y2: i8,
-            color: i8,
-        }
-
         #[derive(Serialize)]
         struct BoardJson {
             stones: Vec<Stone>,
             roots: Vec<Root>,
         }
 
-        let mut stones = Vec::new();
-        let mut roots  = Vec::new();
-        for x in 0..self.width as i8 {
-            for y in 0..self.width as i8 {
-                let idx = (x as usize) * (self.width as usize) + (y as usize);
-                if let Some(color) = self.grids[idx].color {
-                    let color = color as i8;
-                    stones.push(Stone { x, y, color });
-
-                    for dir in self.grids[idx].roots.iter() {
-                        roots.push(Root {
-                            x1: x,
-                            y1: y,
-                            x2: x + dir.0,
-                            y2: y + dir.1,
-                            color,
-                        });
-                    }
-                }
-            }
-        }
-
+        let (stones, roots) = self.stones_and_roots();
         serde_json::to_string(&BoardJson { stones, roots }).unwrap()
     }
     pub fn possible_moves_as_json(&self) -> String {
@@ -565,13 +936,13 @@ impl Board {
         let mut moves = Vec::new();
         for stones in self.possible_moves(Color::Red) {
             moves.push(PossibleMove {
-                stones: stones,
+                stones,
                 color: Color::Red,
             });
         }
         for stones in self.possible_moves(Color::Blue) {
             moves.push(PossibleMove {
-                stones: stones,
+                stones,
                 color: Color::Blue,
             });
         }
@@ -579,8 +950,113 @@ impl Board {
         serde_json::to_string(&moves).unwrap()
     }
 
+    // The regions of a color as JSON, e.g.
+    //     [{"nodes": 250, "scores": true, "cells": [{"x": 0, "y": 0, "parts": [true, ...]}, ...]}, ...]
+    pub fn regions_as_json(&self, color: Color) -> String {
+        serde_json::to_string(&self.regions(color)).unwrap()
+    }
+
+    // The outlines of the regions of a color that score, as JSON, e.g.
+    //     [{"nodes": 250, "polygons": [[[0.0, 0.0], [8.0, 0.0], ...], ...]}, ...]
+    // See `Region::outline` for the coordinates.
+    pub fn region_outlines_as_json(&self, color: Color) -> String {
+        #[derive(Serialize)]
+        struct Outline {
+            nodes:    u16,
+            polygons: Vec<Vec<(f64, f64)>>,
+        }
+        let outlines = self.regions(color).into_iter().filter(|r| r.scores)
+            .map(|r| Outline{nodes: r.nodes, polygons: r.outline()})
+            .collect::<Vec<_>>();
+        serde_json::to_string(&outlines).unwrap()
+    }
+
+    // The ownership map of `playouts` random playouts from the board where
+    // `turn` moves first, as a Float32Array. See `ownership_with` for the
+    // layout.
+    pub fn ownership(&self, turn: Color, playouts: u32, seed0: u32, seed1: u32) -> Vec<f32> {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(convert_seed(seed0, seed1));
+        self.ownership_with(turn, playouts, &mut rng)
+    }
+
+    // Feature planes for machine learning, seen from `side_to_move`. It
+    // returns NUM_PLANES planes of width x width floats, flattened in the
+    // order of [plane][x][y].
+    //
+    //   0      stones of the side to move
+    //   1      stones of the opponent
+    //   2..10  roots of the side to move, one plane per direction (ROOT_DIRS)
+    //   10..18 roots of the opponent
+    //   18     first stones of the possible moves of the side to move
+    //   19..23 region ids of the side to move's graph, one plane per node
+    //          position (N, E, S, W) of each cell. The cell (x, y) is
+    //          surrounded by the grids (x, y) and (x+1, y+1), so the last row
+    //          and column are always 0. The id starts from 1.
+    //   23..27 region ids of the opponent's graph
+    //   27     1 on the edge of the board
+    pub fn to_planes(&self, side_to_move: Color) -> Vec<f32> {
+        let width  = self.width as usize;
+        let area   = width * width;
+        let colors = [side_to_move, opponent_of(side_to_move)];
+        let mut planes = vec![0.0f32; NUM_PLANES * area];
+
+        for (idx, grid) in self.grids.iter().enumerate() {
+            if let Some(color) = grid.color {
+                let side = if color == colors[0] {0} else {1};
+                planes[side * area + idx] = 1.0;
+                for dir in grid.roots.iter() {
+                    let d = ROOT_DIRS.iter().position(|d| d == dir).unwrap();
+                    planes[(2 + side * 8 + d) * area + idx] = 1.0;
+                }
+            }
+            let (x, y) = (idx / width, idx % width);
+            if x == 0 || y == 0 || x == width - 1 || y == width - 1 {
+                planes[27 * area + idx] = 1.0;
+            }
+        }
+        for Move(stone, _, _) in self.possible_moves(side_to_move) {
+            planes[18 * area + stone.x as usize * width + stone.y as usize] = 1.0;
+        }
+
+        for (side, color) in colors.iter().enumerate() {
+            let mut graph = match color {
+                Color::Red  => self.red.clone(),
+                Color::Blue => self.blue.clone(),
+            };
+            graph.score();
+            let ngrids = graph.ngrids as usize;
+            for (idx, node) in graph.nodes.iter().enumerate() {
+                let (cell, pos) = (idx / 4, idx % 4);
+                let (x, y) = (cell / ngrids, cell % ngrids);
+                let plane = 19 + side * 4 + pos;
+                planes[plane * area + x * width + y] = node.region.unwrap() as f32 + 1.0;
+            }
+        }
+        planes
+    }
+
+    // An SVG image of the board. See `svg` for the options.
+    pub fn to_svg(&self, options: &svg::SvgOptions) -> String {
+        svg::board_svg(self, options)
+    }
+
+    // Draws the board like the canvas of the web page and returns the RGBA
+    // pixels of a render::IMAGE_WIDTH x render::IMAGE_HEIGHT image.
+    pub fn render_rgba(&self, red_name: &str, blue_name: &str, message: &str, guide: bool) -> Vec<u8> {
+        let options = render::RenderOptions{
+            red_name:  red_name.to_string(),
+            blue_name: blue_name.to_string(),
+            message:   message.to_string(),
+            guide,
+            ..render::RenderOptions::default()
+        };
+        render::render(self, &options).pixels
+    }
+
     fn playout<R:Rng>(&mut self, init_turn: Color, rng: &mut R) -> Option<Color> {
         let next_turn = opponent_of(init_turn);
+        // is_decided() is not checked here because it costs much more than
+        // the random moves it could save.
         while !self.is_gameover() {
             {
                 let moves = self.possible_moves(init_turn);
@@ -607,6 +1083,151 @@ impl Board {
     }
 }
 
+impl Board {
+    // The regions of a color that make the score, see `Region`. The number
+    // of regions that score is `score(color)`.
+    pub fn regions(&self, color: Color) -> Vec<Region> {
+        match color {
+            Color::Red  => self.red .clone().regions(),
+            Color::Blue => self.blue.clone().regions(),
+        }
+    }
+
+    // The fraction of the random playouts (`playout`) in which each node ends
+    // up in a region that scores, for Red and then for Blue. The nodes are
+    // in the order of `Graph`, i.e. the node `pos` (N, E, S, W) of the cell
+    // (x, y) is at `(x * (width-1) + y) * 4 + pos`, and Blue's map follows
+    // Red's. All 0 if `playouts` is 0.
+    pub fn ownership_with<R: Rng>(&self, turn: Color, playouts: u32, rng: &mut R) -> Vec<f32> {
+        let nnodes = self.red.nodes.len();
+        let mut counts = vec![0u32; 2 * nnodes];
+        for _ in 0..playouts {
+            let mut board = self.clone();
+            board.playout(turn, rng);
+            for (side, graph) in [&mut board.red, &mut board.blue].iter_mut().enumerate() {
+                let sizes = graph.region_sizes();
+                for (idx, node) in graph.nodes.iter().enumerate() {
+                    if 4 < sizes[node.region.unwrap() as usize] {
+                        counts[side * nnodes + idx] += 1;
+                    }
+                }
+            }
+        }
+        counts.into_iter()
+            .map(|c| if playouts == 0 {0.0} else {c as f32 / playouts as f32})
+            .collect()
+    }
+
+    // stones and roots on the board, in the order of the grids. It is used by
+    // `to_json` and `svg`.
+    fn stones_and_roots(&self) -> (Vec<Stone>, Vec<Root>) {
+        let mut stones = Vec::new();
+        let mut roots  = Vec::new();
+        for x in 0..self.width as i8 {
+            for y in 0..self.width as i8 {
+                let idx = (x as usize) * (self.width as usize) + (y as usize);
+                if let Some(color) = self.grids[idx].color {
+                    let color = color as i8;
+                    stones.push(Stone { x, y, color });
+
+                    for dir in self.grids[idx].roots.iter() {
+                        roots.push(Root {
+                            x1: x,
+                            y1: y,
+                            x2: x + dir.0,
+                            y2: y + dir.1,
+                            color,
+                        });
+                    }
+                }
+            }
+        }
+        (stones, roots)
+    }
+}
+
+// A key of Zobrist hashing. Instead of a table of random numbers, the key of
+// each feature is made by mixing its index (splitmix64), so that the hash is
+// the same for any build and board size.
+fn zobrist_key(index: u64) -> u64 {
+    let mut z = index.wrapping_add(1).wrapping_mul(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+// The symmetries of the board that keep the colors of the initial stones:
+// the identity, the 180 degree rotation and the reflections along the two
+// diagonals. Each of them is its own inverse.
+pub const NUM_SYMMETRIES: usize = 4;
+
+fn transform_dir(symmetry: usize, dir: Dir) -> Dir {
+    let Dir(dx, dy) = dir;
+    match symmetry {
+        0 => Dir( dx,  dy),
+        1 => Dir(-dx, -dy),
+        2 => Dir( dy,  dx),
+        _ => Dir(-dy, -dx),
+    }
+}
+
+impl Coord {
+    fn transformed(&self, symmetry: usize, width: usize) -> Coord {
+        let last = width as i8 - 1;
+        match symmetry {
+            0 => Coord::new(self.x, self.y),
+            1 => Coord::new(last - self.x, last - self.y),
+            2 => Coord::new(self.y, self.x),
+            _ => Coord::new(last - self.y, last - self.x),
+        }
+    }
+}
+
+impl Move {
+    // the move on the board transformed by a symmetry (see `NUM_SYMMETRIES`)
+    pub fn transformed(&self, symmetry: usize, width: usize) -> Move {
+        let Move(stone1, stone2, stone3) = *self;
+        Move(stone1.transformed(symmetry, width),
+             stone2.transformed(symmetry, width),
+             stone3.transformed(symmetry, width))
+    }
+}
+
+impl Board {
+    // A hash of the stones and roots on the board. The graphs are not
+    // hashed because they are determined by the roots.
+    pub fn zobrist_hash(&self) -> u64 {
+        self.symmetric_hash(0)
+    }
+
+    // the hash of the board transformed by a symmetry
+    pub fn symmetric_hash(&self, symmetry: usize) -> u64 {
+        let width = self.width as usize;
+        let mut hash = zobrist_key(self.width as u64);
+        for (idx, grid) in self.grids.iter().enumerate() {
+            if let Some(color) = grid.color {
+                let at  = Coord::new((idx / width) as i8, (idx % width) as i8).transformed(symmetry, width);
+                let idx = at.x as u64 * width as u64 + at.y as u64;
+                // a stone and 8 directions of roots for each color
+                let base = 32 + (idx * 2 + color as u64) * 9;
+                hash ^= zobrist_key(base);
+                for dir in grid.roots.iter() {
+                    let dir = transform_dir(symmetry, *dir);
+                    let d = ROOT_DIRS.iter().position(|d| *d == dir).unwrap() as u64;
+                    hash ^= zobrist_key(base + 1 + d);
+                }
+            }
+        }
+        hash
+    }
+
+    // The minimum hash over the symmetries and the symmetry that gives it.
+    // Boards that are the same modulo symmetry have the same canonical hash.
+    pub fn canonical_hash(&self) -> (u64, usize) {
+        (0..NUM_SYMMETRIES).map(|s| (self.symmetric_hash(s), s)).min().unwrap()
+    }
+}
+
 fn convert_seed(seed0: u32, seed1: u32) -> u64 {
     (seed0 as u64) + ((seed1 as u64) << 32)
 }
@@ -624,19 +1245,125 @@ impl RandomPlayer {
         RandomPlayer{color, rng: rand::rngs::StdRng::seed_from_u64(seed)}
     }
     pub fn play(&mut self, mut board: Board) -> Board {
+        if let Some(next_move) = self.genmove(&board) {
+            board.apply_move(next_move, self.color);
+        }
+        board
+    }
+}
+
+impl RandomPlayer {
+    fn genmove(&mut self, board: &Board) -> Option<Move> {
         let moves = board.possible_moves(self.color);
-        if !moves.is_empty() {
-            board.apply_move(moves[self.rng.gen_range(0, moves.len())], self.color);
+        if moves.is_empty() {
+            return None;
+        }
+        Some(moves[self.rng.gen_range(0, moves.len())])
+    }
+}
+
+#[wasm_bindgen]
+pub struct GreedyPlayer {
+    pub color: Color,
+    rng: rand::rngs::StdRng,
+    lookahead: bool,
+}
+
+#[wasm_bindgen]
+impl GreedyPlayer {
+    pub fn new(color: Color, seed0: u32, seed1: u32, lookahead: bool) -> Self {
+        let seed = convert_seed(seed0, seed1);
+        GreedyPlayer{color, rng: rand::rngs::StdRng::seed_from_u64(seed), lookahead}
+    }
+    pub fn play(&mut self, mut board: Board) -> Board {
+        if let Some(next_move) = self.genmove(&board) {
+            board.apply_move(next_move, self.color);
         }
         board
     }
 }
 
+impl GreedyPlayer {
+    fn genmove(&mut self, board: &Board) -> Option<Move> {
+        board.greedy_move(self.color, self.lookahead, &mut self.rng)
+    }
+}
+
+impl Board {
+    // evaluate each move by (own score gain) - (opponent score gain).
+    // Since a move changes only the score of the player, the opponent's gain
+    // is considered only if `lookahead` is set, assuming the best reply.
+    fn greedy_values(&self, turn: Color, lookahead: bool) -> Vec<(Move, i32)> {
+        let opponent = opponent_of(turn);
+        let mut current = self.clone();
+        let own_score = current.score(turn) as i32;
+        let opp_score = current.score(opponent) as i32;
+
+        self.possible_moves(turn).into_iter().map(|next_move| {
+            let mut next_board = self.clone();
+            next_board.apply_move(next_move, turn);
+
+            let mut value = next_board.score(turn) as i32 - own_score;
+            if lookahead {
+                let reply = next_board.possible_moves(opponent).iter().map(|m| {
+                    let mut reply_board = next_board.clone();
+                    reply_board.apply_move(*m, opponent);
+                    reply_board.score(opponent) as i32 - opp_score
+                }).max().unwrap_or(0);
+                value -= reply;
+            }
+            (next_move, value)
+        }).collect()
+    }
+
+    // choose the move with the best greedy value. Ties are broken randomly.
+    fn greedy_move<R: Rng>(&self, turn: Color, lookahead: bool, rng: &mut R) -> Option<Move> {
+        let values = self.greedy_values(turn, lookahead);
+        let best_value = values.iter().map(|(_, v)| *v).max()?;
+        let best_moves = values.iter()
+            .filter(|(_, v)| *v == best_value)
+            .map(|(m, _)| *m)
+            .collect::<Vec<_>>();
+        best_moves.choose(rng).copied()
+    }
+}
+
+// sample an index with probability proportional to exp(value / temperature).
+// If temperature is zero, it returns the index of the max value.
+fn sample_softmax<R: Rng>(values: &[f64], temperature: f64, rng: &mut R) -> usize {
+    assert!(!values.is_empty());
+    let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    if 0.0 < temperature && max == f64::NEG_INFINITY {
+        // every weight is zero, e.g. ln(0) when no candidate has ever won
+        return rng.gen_range(0, values.len());
+    }
+    if temperature <= 0.0 || !max.is_finite() {
+        return values.iter().position(|v| *v == max).unwrap();
+    }
+    // subtract max to avoid overflow
+    let weights = values.iter()
+        .map(|v| f64::exp((v - max) / temperature))
+        .collect::<Vec<_>>();
+    let mut r = rng.gen::<f64>() * weights.iter().sum::<f64>();
+    for (i, w) in weights.iter().enumerate() {
+        if r < *w {
+            return i;
+        }
+        r -= w;
+    }
+    values.len() - 1
+}
+
 #[wasm_bindgen]
 pub struct NaiveMonteCarlo {
     pub color: Color,
     rng: rand::rngs::StdRng,
     time_limit: Duration,
+    temperature: f64,
+    temperature_moves: usize,
+    resign_threshold: f64,
+    resigned: bool,
+    last_search: Option<SearchInfo>,
 }
 
 #[wasm_bindgen]
@@ -647,10 +1374,43 @@ impl NaiveMonteCarlo {
             color,
             rng: rand::rngs::StdRng::seed_from_u64(seed),
             time_limit: Duration::from_secs(timelimit as u64),
+            temperature: 0.0,
+            temperature_moves: 0,
+            resign_threshold: 0.0,
+            resigned: false,
+            last_search: None,
         }
     }
 
-    pub fn play(&mut self, board: Board) -> Board {
+    // If the estimated win rate of the best move is less than the threshold,
+    // it resigns instead of playing a move.
+    pub fn set_resign_threshold(&mut self, threshold: f64) {
+        self.resign_threshold = threshold;
+    }
+
+    pub fn has_resigned(&self) -> bool {
+        self.resigned
+    }
+
+    // Until `moves` moves are played on the board, the next move is sampled
+    // in proportion to wins^(1/temperature). After that, it plays the best.
+    pub fn set_temperature(&mut self, temperature: f64, moves: u32) {
+        self.temperature       = temperature;
+        self.temperature_moves = moves as usize;
+    }
+
+    pub fn play(&mut self, mut board: Board) -> Board {
+        if let Some(next_move) = self.genmove(&board) {
+            board.apply_move(next_move, self.color);
+        }
+        board
+    }
+}
+
+impl NaiveMonteCarlo {
+    fn genmove(&mut self, board: &Board) -> Option<Move> {
+        // the last decision does not hold, e.g. after undo or in a new game
+        self.resigned = false;
 
         let mut candidates = Vec::<(_, _, u32)>::new();
         for possible_move in board.possible_moves(self.color).iter() {
@@ -660,7 +1420,7 @@ impl NaiveMonteCarlo {
         }
 
         if candidates.is_empty() {
-            return board
+            return None
         }
 
 //         console_log!("{} possible moves are there", candidates.len());
@@ -681,20 +1441,52 @@ impl NaiveMonteCarlo {
 //             .map(|x| x.2 as f64 / samples as f64).collect::<Vec<_>>());
 
         candidates.sort_by_key(|x| x.2);
+        let best_rate = candidates.last().unwrap().2 as f64 / samples as f64;
+        self.last_search = Some(SearchInfo{
+            nodes:    (samples * candidates.len()) as u64,
+            win_rate: Some(best_rate),
+            ..SearchInfo::default()
+        });
+        if best_rate < self.resign_threshold {
+            console_log!("{:?}, estimated win rate = {}. resign.", self.color, best_rate);
+            self.resigned = true;
+            return None;
+        }
+
+        let chosen = if board.num_moves() < self.temperature_moves {
+            // ln(wins)/T in softmax results in wins^(1/T), as in UCTMonteCarlo.
+            // The win rates themselves are too close to each other for that.
+            let wins = candidates.iter()
+                .map(|x| f64::ln(x.2 as f64)).collect::<Vec<_>>();
+            let idx = sample_softmax(&wins, self.temperature, &mut self.rng);
+            candidates.swap_remove(idx)
+        } else {
+            candidates.pop().unwrap()
+        };
         console_log!("{:?}, estimated win rate = {}.", self.color,
-                     candidates.last().unwrap().2 as f64 / samples as f64);
-        candidates.pop().unwrap().1
+                     chosen.2 as f64 / samples as f64);
+        if let Some(ref mut info) = self.last_search {
+            info.win_rate = Some(chosen.2 as f64 / samples as f64);
+        }
+        Some(chosen.0)
     }
 }
 
 #[wasm_bindgen]
 pub struct UCTMonteCarlo {
-    pub color:        Color,
-    rng:              rand::rngs::StdRng,
-    time_limit:       Duration,
-    ucb1_coeff:       f64,
-    expand_threshold: u32,
-    root:             Rc<RefCell<UCTNode>>,
+    pub color:         Color,
+    rng:               rand::rngs::StdRng,
+    time_limit:        Duration,
+    ucb1_coeff:        f64,
+    expand_threshold:  u32,
+    max_playouts:      u32,
+    temperature:       f64,
+    temperature_moves: usize,
+    resign_threshold:  f64,
+    resigned:          bool,
+    last_search:       Option<SearchInfo>,
+    last_visits:       Vec<(Move, u32)>, // samples of each move in the last search
+    root:              Rc<RefCell<UCTNode>>,
 }
 
 #[derive(Debug, Clone)]
@@ -706,11 +1498,12 @@ struct UCTNode {
     parent:   Weak<RefCell<UCTNode>>,
     color:    Color,
     board:    Board,
+    mv:       Option<Move>, // the move that results in this node. None if passed
 }
 
 impl UCTNode {
     fn new(color: Color, board: Board) -> Self {
-        UCTNode{win: 0, lose: 0, samples: 0, children: Vec::new(), parent: Weak::new(), color, board}
+        UCTNode{win: 0, lose: 0, samples: 0, children: Vec::new(), parent: Weak::new(), color, board, mv: None}
     }
 
     fn win_rate(&self) -> f64 {
@@ -749,6 +1542,7 @@ fn expand_node(node_ptr: &Rc<RefCell<UCTNode>>) {
                 UCTNode::new(opponent_of(node.color), possible_board)));
 
         child.borrow_mut().parent = Rc::downgrade(node_ptr);
+        child.borrow_mut().mv     = Some(*possible_move);
         node.children.push(child);
     }
 
@@ -760,7 +1554,7 @@ fn expand_node(node_ptr: &Rc<RefCell<UCTNode>>) {
         child.borrow_mut().parent = Rc::downgrade(node_ptr);
         node.children.push(child);
     }
-    assert!(0 < node.children.len());
+    assert!(!node.children.is_empty());
 }
 
 // fn count_node_and_depth(root: Rc<RefCell<UCTNode>>, depth: usize) -> (usize, usize) {
@@ -797,26 +1591,67 @@ impl UCTMonteCarlo {
             time_limit: Duration::from_secs(timelimit as u64),
             ucb1_coeff,
             expand_threshold,
+            max_playouts: u32::MAX,
+            temperature: 0.0,
+            temperature_moves: 0,
+            resign_threshold: 0.0,
+            resigned: false,
+            last_search: None,
+            last_visits: Vec::new(),
             root,
         }
     }
 
-    pub fn play(&mut self, board: Board) -> Board {
+    // If the estimated win rate of the current state is less than the
+    // threshold, it resigns instead of playing a move.
+    pub fn set_resign_threshold(&mut self, threshold: f64) {
+        self.resign_threshold = threshold;
+    }
+
+    pub fn has_resigned(&self) -> bool {
+        self.resigned
+    }
+
+    // Until `moves` moves are played on the board, the next move is sampled
+    // in proportion to samples^(1/temperature). After that, it plays the best.
+    pub fn set_temperature(&mut self, temperature: f64, moves: u32) {
+        self.temperature       = temperature;
+        self.temperature_moves = moves as usize;
+    }
+
+    pub fn play(&mut self, mut board: Board) -> Board {
+        if let Some(next_move) = self.genmove(&board) {
+            board.apply_move(next_move, self.color);
+        }
+        board
+    }
+}
+
+impl UCTMonteCarlo {
+    fn genmove(&mut self, board: &Board) -> Option<Move> {
+        self.resigned = false;
         if !board.can_move(self.color) {
-            return board;
+            return None;
         }
 
         // find the current state from the children of root node
-        // (means: find opponent's move)
-        let tmp = Rc::clone(self.root.borrow().children.iter()
-            .find(|x| x.borrow().board.grids == board.grids).unwrap());
-        self.root = tmp;
+        // (means: find opponent's move). If the board is not in the tree
+        // (e.g. someone else played for us), start from a new tree.
+        let found = self.root.borrow().children.iter()
+            .find(|x| x.borrow().board.grids == board.grids).map(Rc::clone);
+        self.root = found.unwrap_or_else(||
+            Rc::new(RefCell::new(UCTNode::new(self.color, board.clone()))));
         self.root.borrow_mut().parent = Weak::new(); // discard ancesters
         assert_eq!(self.root.borrow().color, self.color);
+        if self.root.borrow().children.is_empty() {
+            expand_node(&self.root);
+        }
 
-        // search and expand the tree
+        // search and expand the tree. The samples reused from the last search
+        // do not count for max_playouts.
         let stop = Instant::now() + self.time_limit;
-        while Instant::now() < stop {
+        let reused = self.root.borrow().samples;
+        while Instant::now() < stop && self.root.borrow().samples - reused < self.max_playouts {
             let mut node = Rc::clone(&self.root);
             let mut depth = 0;
             let logn = f64::ln(node.borrow().samples as f64);
@@ -841,10 +1676,9 @@ impl UCTMonteCarlo {
             node.borrow_mut().samples += 1;
 
             // do this after `samples += 1`
-            if self.expand_threshold <= node.borrow().samples {
-                if !node.borrow().board.is_gameover() {
-                    expand_node(&node);
-                }
+            if self.expand_threshold <= node.borrow().samples &&
+               !node.borrow().board.is_gameover() {
+                expand_node(&node);
             }
 
             while let Some(parent) = Rc::clone(&node).borrow().parent.upgrade() {
@@ -868,25 +1702,338 @@ impl UCTMonteCarlo {
 //             console_log!("{} nodes with depth {} is used", n, d);
 //         }
 
+        // root.lose counts the wins of root.color, i.e. our wins.
+        let win_rate = self.root.borrow().lose_rate();
+        self.last_search = Some(SearchInfo{
+            nodes:    self.root.borrow().samples as u64,
+            win_rate: Some(win_rate),
+            ..SearchInfo::default()
+        });
+        self.last_visits = self.root.borrow().children.iter()
+            .filter_map(|x| x.borrow().mv.map(|m| (m, x.borrow().samples)))
+            .collect();
+        if win_rate < self.resign_threshold {
+            console_log!("{:?}, estimated win rate = {}. resign.", self.color, win_rate);
+            self.resigned = true;
+            return None;
+        }
+
         // choose the next root by chosing the node with max win rate
-        let tmp = Rc::clone(self.root.borrow_mut().children.iter()
-            .max_by(|a, b|    (a.borrow().win_rate() - a.borrow().lose_rate())
-                .partial_cmp(&(b.borrow().win_rate() - b.borrow().lose_rate()))
-                .unwrap_or(std::cmp::Ordering::Less))
-            .unwrap());
+        let tmp = if board.num_moves() < self.temperature_moves {
+            // or, sample a node depending on the number of samples.
+            // ln(n)/T in softmax results in n^(1/T).
+            let root = self.root.borrow();
+            let samples = root.children.iter()
+                .map(|x| f64::ln(x.borrow().samples as f64)).collect::<Vec<_>>();
+            Rc::clone(&root.children[sample_softmax(&samples, self.temperature, &mut self.rng)])
+        } else {
+            Rc::clone(self.root.borrow_mut().children.iter()
+                .max_by(|a, b|    (a.borrow().win_rate() - a.borrow().lose_rate())
+                    .partial_cmp(&(b.borrow().win_rate() - b.borrow().lose_rate()))
+                    .unwrap_or(std::cmp::Ordering::Less))
+                .unwrap())
+        };
         self.root = tmp;
         self.root.borrow_mut().parent = Weak::new(); // discard ancesters
         console_log!("{:?}, estimated win rate = {}, lose rate = {}.", self.color,
                      self.root.borrow().win_rate(), self.root.borrow().lose_rate());
 
+        // we need the opponent's moves in the tree to find the next state.
         if self.root.borrow().children.is_empty() &&
           !self.root.borrow().board.is_gameover() {
-            console_log!("root.children is empty. Too short time limit?");
+            expand_node(&self.root);
         }
         assert!(!self.root.borrow().children.is_empty() ||
                  self.root.borrow().board.is_gameover());
-        // return the board in the root node
-        self.root.borrow().board.clone()
+        // return the move that leads to the root node
+        self.root.borrow().mv
+    }
+}
+
+// weights of the static evaluation used by AlphaBetaPlayer.
+// A region is worth much more than anything else; potential cuts (moves that
+// can close a loop) come next and mobility is used to break ties.
+const EVAL_WIN:      i32 = 1_000_000;
+const EVAL_REGION:   i32 = 256;
+const EVAL_CUT:      i32 = 16;
+const EVAL_MOBILITY: i32 = 1;
+
+#[wasm_bindgen]
+pub struct AlphaBetaPlayer {
+    pub color:   Color,
+    depth:       u32,
+    time_limit:  Duration,
+    stop:        Instant,
+    nodes:       u64,
+    last_search: Option<SearchInfo>,
+}
+
+// potential cuts: moves that put the last stone on the player's own stone or
+// on the edge of the board. Those are the moves that can close a loop.
+fn count_potential_cuts(board: &Board, moves: &[Move], turn: Color) -> i32 {
+    let upper = board.width as i8 - 1;
+    moves.iter().filter(|Move(_, _, stone3)| {
+        let idx = stone3.x as usize * board.width as usize + stone3.y as usize;
+        board.grids[idx].color == Some(turn) ||
+            stone3.x == 0 || stone3.y == 0 || stone3.x == upper || stone3.y == upper
+    }).count() as i32
+}
+
+// static evaluation of the board from the viewpoint of `color`.
+fn evaluate(board: &mut Board, color: Color) -> i32 {
+    let opponent = opponent_of(color);
+    let regions = board.score(color) as i32 - board.score(opponent) as i32;
+
+    if board.is_gameover() {
+        return regions.signum() * EVAL_WIN + regions;
+    }
+
+    let own_moves = board.possible_moves(color);
+    let opp_moves = board.possible_moves(opponent);
+    let mobility = own_moves.len() as i32 - opp_moves.len() as i32;
+    let cuts = count_potential_cuts(board, &own_moves, color) -
+               count_potential_cuts(board, &opp_moves, opponent);
+
+    EVAL_REGION * regions + EVAL_CUT * cuts + EVAL_MOBILITY * mobility
+}
+
+#[wasm_bindgen]
+impl AlphaBetaPlayer {
+    pub fn new(color: Color, depth: u32, timelimit: u32) -> Self {
+        assert!(0 < depth);
+        AlphaBetaPlayer{
+            color,
+            depth,
+            time_limit: Duration::from_secs(timelimit as u64),
+            stop: Instant::now(),
+            nodes: 0,
+            last_search: None,
+        }
+    }
+
+    pub fn play(&mut self, mut board: Board) -> Board {
+        if let Some(next_move) = self.genmove(&board) {
+            board.apply_move(next_move, self.color);
+        }
+        board
+    }
+}
+
+impl AlphaBetaPlayer {
+    fn genmove(&mut self, board: &Board) -> Option<Move> {
+        self.search(board)
+    }
+
+    // iterative deepening. If the time runs out, the best move found in the
+    // last completed iteration is returned.
+    fn search(&mut self, board: &Board) -> Option<Move> {
+        let mut moves = board.possible_moves(self.color);
+        if moves.len() <= 1 {
+            self.last_search = Some(SearchInfo{depth: Some(0), ..SearchInfo::default()});
+            return moves.pop();
+        }
+
+        self.stop  = Instant::now() + self.time_limit;
+        self.nodes = 0;
+
+        let mut best = (moves[0], -EVAL_WIN * 2);
+        let mut completed = 0;
+        for depth in 1..=self.depth {
+            let mut alpha = -EVAL_WIN * 2;
+            let mut current = None;
+            for next_move in moves.iter() {
+                let mut next_board = board.clone();
+                next_board.apply_move(*next_move, self.color);
+
+                let value = match self.negamax(&next_board,
+                        opponent_of(self.color), depth - 1, -EVAL_WIN * 2, -alpha) {
+                    Some(value) => -value,
+                    None        => break, // time is up
+                };
+                if alpha < value {
+                    alpha   = value;
+                    current = Some((*next_move, value));
+                }
+            }
+            if Instant::now() >= self.stop {
+                break; // the last iteration is incomplete. discard it.
+            }
+            best = current.unwrap();
+            completed = depth;
+
+            // search the best move first in the next iteration
+            let idx = moves.iter().position(|m| *m == best.0).unwrap();
+            moves[..=idx].rotate_right(1);
+        }
+        console_log!("{:?}, evaluation = {}, {} nodes searched.",
+                     self.color, best.1, self.nodes);
+        self.last_search = Some(SearchInfo{
+            nodes:      self.nodes,
+            depth:      Some(completed),
+            evaluation: Some(best.1).filter(|_| 0 < completed),
+            ..SearchInfo::default()
+        });
+        Some(best.0)
+    }
+
+    // returns the value of the board from the viewpoint of `turn`, or None if
+    // the time is up.
+    fn negamax(&mut self, board: &Board, turn: Color, depth: u32,
+               mut alpha: i32, beta: i32) -> Option<i32> {
+        if Instant::now() >= self.stop {
+            return None;
+        }
+        self.nodes += 1;
+
+        let mut board = board.clone();
+        if depth == 0 || board.is_gameover() {
+            return Some(evaluate(&mut board, turn));
+        }
+
+        let moves = board.possible_moves(turn);
+        if moves.is_empty() {
+            // pass. the same board is passed to the opponent
+            return self.negamax(&board, opponent_of(turn), depth - 1, -beta, -alpha)
+                .map(|value| -value);
+        }
+
+        for next_move in moves.iter() {
+            let mut next_board = board.clone();
+            next_board.apply_move(*next_move, turn);
+            let value = -self.negamax(&next_board, opponent_of(turn), depth - 1, -beta, -alpha)?;
+            if alpha < value {
+                alpha = value;
+            }
+            if beta <= alpha {
+                break;
+            }
+        }
+        Some(alpha)
+    }
+}
+
+// Engine settings for each strength level.
+//   - playouts:     UCT playouts per move. If 0, a tree search is not used.
+//   - depth:        alpha-beta search depth. If both playouts and depth are 0,
+//                   a greedy one-ply search is used.
+//   - temperature:  temperature of softmax move selection.
+//   - blunder_rate: probability of playing a random move instead.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Strength {
+    playouts:     u32,
+    depth:        u32,
+    temperature:  f64,
+    blunder_rate: f64,
+}
+
+const MIN_LEVEL: u32 = 1;
+const MAX_LEVEL: u32 = 10;
+
+// Each level should be stronger than the previous one. The scores of each
+// level against the previous one in 400-game matches on a 9x9 board (see
+// `tests::calibrate_levels`) are below, accurate to about +/- 35 Elo. From
+// level 5, each level is about 100 Elo stronger than the previous one. The
+// levels 3 to 5 are closer to each other.
+//
+//     level       2     3     4     5     6     7     8     9    10
+//     score   278.0 224.5 212.5 232.0 254.0 253.0 252.5 256.5 252.0  (of 400)
+//     Elo gap   143    43    22    56    96    94    93   101    92
+const STRENGTH_LEVELS: [Strength; 10] = [
+    Strength{playouts:     0, depth: 0, temperature: 1.0, blunder_rate: 0.5 }, //  1
+    Strength{playouts:     0, depth: 1, temperature: 0.0, blunder_rate: 0.5 }, //  2
+    Strength{playouts:     0, depth: 1, temperature: 0.0, blunder_rate: 0.25}, //  3
+    Strength{playouts:     0, depth: 1, temperature: 0.0, blunder_rate: 0.1 }, //  4
+    Strength{playouts:     0, depth: 2, temperature: 0.0, blunder_rate: 0.0 }, //  5
+    Strength{playouts:     0, depth: 3, temperature: 0.0, blunder_rate: 0.05}, //  6
+    Strength{playouts:    30, depth: 0, temperature: 0.0, blunder_rate: 0.05}, //  7
+    Strength{playouts:    40, depth: 0, temperature: 0.0, blunder_rate: 0.0 }, //  8
+    Strength{playouts:    80, depth: 0, temperature: 0.0, blunder_rate: 0.0 }, //  9
+    Strength{playouts:   150, depth: 0, temperature: 0.0, blunder_rate: 0.0 }, // 10
+];
+
+enum LeveledEngine {
+    Greedy,
+    AlphaBeta(AlphaBetaPlayer),
+    MonteCarlo(Box<UCTMonteCarlo>),
+}
+
+#[wasm_bindgen]
+pub struct LeveledPlayer {
+    pub color:   Color,
+    rng:         rand::rngs::StdRng,
+    strength:    Strength,
+    engine:      LeveledEngine,
+    last_search: Option<SearchInfo>,
+}
+
+#[wasm_bindgen]
+impl LeveledPlayer {
+    pub fn new(color: Color, seed0: u32, seed1: u32, level: u32, timelimit: u32, board_width: usize) -> Self {
+        let level = level.clamp(MIN_LEVEL, MAX_LEVEL);
+        let strength = STRENGTH_LEVELS[(level - MIN_LEVEL) as usize];
+        let mut rng = rand::rngs::StdRng::seed_from_u64(convert_seed(seed0, seed1));
+
+        let engine = if 0 < strength.playouts {
+            let mut uct = UCTMonteCarlo::new(color, rng.gen(), rng.gen(), timelimit,
+                                             2.0, 3, board_width);
+            uct.max_playouts = strength.playouts;
+            uct.set_temperature(strength.temperature, u32::MAX);
+            LeveledEngine::MonteCarlo(Box::new(uct))
+        } else if 0 < strength.depth {
+            LeveledEngine::AlphaBeta(AlphaBetaPlayer::new(color, strength.depth, timelimit))
+        } else {
+            LeveledEngine::Greedy
+        };
+
+        LeveledPlayer{
+            color,
+            rng,
+            strength,
+            engine,
+            last_search: None,
+        }
+    }
+
+    pub fn play(&mut self, mut board: Board) -> Board {
+        if let Some(next_move) = self.genmove(&board) {
+            board.apply_move(next_move, self.color);
+        }
+        board
+    }
+}
+
+impl LeveledPlayer {
+    fn genmove(&mut self, board: &Board) -> Option<Move> {
+        self.last_search = None;
+        if self.rng.gen::<f64>() < self.strength.blunder_rate {
+            let moves = board.possible_moves(self.color);
+            if moves.is_empty() {
+                return None;
+            }
+            return Some(moves[self.rng.gen_range(0, moves.len())]);
+        }
+
+        match self.engine {
+            LeveledEngine::Greedy => {
+                let values = board.greedy_values(self.color, false);
+                if values.is_empty() {
+                    return None;
+                }
+                let weights = values.iter().map(|(_, v)| *v as f64).collect::<Vec<_>>();
+                let idx = sample_softmax(&weights, self.strength.temperature, &mut self.rng);
+                Some(values[idx].0)
+            }
+            LeveledEngine::AlphaBeta(ref mut player) => {
+                let next_move = player.genmove(board);
+                self.last_search = player.last_search.clone();
+                next_move
+            }
+            LeveledEngine::MonteCarlo(ref mut player) => {
+                let next_move = player.genmove(board);
+                self.last_search = player.last_search.clone();
+                next_move
+            }
+        }
     }
 }
 
@@ -895,6 +2042,104 @@ mod tests {
     use super::*;
     use serde_json::{json, Value};
 
+    // play randomly until red gets a chance to make a new region
+    fn random_board_where_red_can_score(seed: u64) -> Board {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+        let mut board = Board::new(9);
+        loop {
+            assert!(!board.is_gameover());
+            let current = board.score(Color::Red);
+            let gains = board.possible_moves(Color::Red).iter().any(|m| {
+                let mut next = board.clone();
+                next.apply_move(*m, Color::Red);
+                current < next.score(Color::Red)
+            });
+            if gains {
+                break;
+            }
+            for turn in [Color::Red, Color::Blue].iter() {
+                let moves = board.possible_moves(*turn);
+                if !moves.is_empty() {
+                    board.apply_move(moves[rng.gen_range(0, moves.len())], *turn);
+                }
+            }
+        }
+        board
+    }
+
+    #[test]
+    fn regions() {
+        let mut board = random_board_where_red_can_score(3);
+        for color in [Color::Red, Color::Blue].iter() {
+            let regions = board.regions(*color);
+            assert_eq!(regions.iter().map(|r| r.nodes as usize).sum::<usize>(), 8 * 8 * 4);
+            assert_eq!(regions.iter().filter(|r| r.scores).count(), board.score(*color) as usize);
+            for region in regions.iter() {
+                let parts = region.cells.iter()
+                    .map(|c| c.parts.iter().filter(|p| **p).count()).sum::<usize>();
+                assert_eq!(parts, region.nodes as usize);
+                assert_eq!(region.scores, 4 < region.nodes);
+            }
+        }
+        let json: Value = serde_json::from_str(&board.regions_as_json(Color::Red)).unwrap();
+        assert_eq!(json.as_array().unwrap().len(), board.regions(Color::Red).len());
+        assert_eq!(json[0]["cells"][0], json!({"x": 0, "y": 0, "parts": [true, true, true, true]}));
+    }
+
+    #[test]
+    fn region_outlines() {
+        let board = Board::new(5);
+        let regions = board.regions(Color::Red);
+        assert_eq!(regions.len(), 1);
+        assert_eq!(regions[0].outline(), vec![vec![(0.0, 0.0), (4.0, 0.0), (4.0, 4.0), (0.0, 4.0)]]);
+
+        // the signed area of the polygons is the area of the triangles
+        let board = random_board_where_red_can_score(5);
+        for color in [Color::Red, Color::Blue].iter() {
+            for region in board.regions(*color).iter() {
+                let area = region.outline().iter().map(|polygon| {
+                    let n = polygon.len();
+                    (0..n).map(|i| {
+                        let (p, q) = (polygon[i], polygon[(i + 1) % n]);
+                        p.0 * q.1 - q.0 * p.1
+                    }).sum::<f64>() / 2.0
+                }).sum::<f64>();
+                assert_eq!(area, region.nodes as f64 / 4.0);
+            }
+        }
+        let json: Value = serde_json::from_str(&board.region_outlines_as_json(Color::Blue)).unwrap();
+        assert_eq!(json.as_array().unwrap().len(),
+                   board.regions(Color::Blue).iter().filter(|r| r.scores).count());
+    }
+
+    #[test]
+    fn ownership() {
+        let board = Board::new(5);
+        let map = board.ownership(Color::Red, 20, 1, 2);
+        assert_eq!(map.len(), 2 * 4 * 4 * 4);
+        assert!(map.iter().all(|f| 0.0 <= *f && *f <= 1.0));
+        assert!(board.ownership(Color::Red, 0, 1, 2).iter().all(|f| *f == 0.0));
+
+        // nothing changes after the game is over
+        let mut rng = rand::rngs::StdRng::seed_from_u64(42);
+        let mut board = Board::new(5);
+        board.playout(Color::Red, &mut rng);
+        let map = board.ownership_with(Color::Red, 3, &mut rng);
+        for (side, color) in [Color::Red, Color::Blue].iter().enumerate() {
+            let mut owned = vec![0.0; 4 * 4 * 4];
+            for region in board.regions(*color).iter().filter(|r| r.scores) {
+                for cell in region.cells.iter() {
+                    for pos in 0..4 {
+                        if cell.parts[pos] {
+                            owned[(cell.x as usize * 4 + cell.y as usize) * 4 + pos] = 1.0;
+                        }
+                    }
+                }
+            }
+            assert_eq!(map[side * 64..(side + 1) * 64], owned[..]);
+        }
+    }
+
     #[test]
     fn possible_moves() {
         let board = Board::new(9);
@@ -988,4 +2233,306 @@ mod tests {
             assert!(roots.contains(&correct));
         }
     }
+
+    #[test]
+    fn alphabeta_plays_a_valid_move() {
+        let board = Board::new(9);
+        let mut player = AlphaBetaPlayer::new(Color::Red, 2, 60);
+        let next_move = player.search(&board).unwrap();
+        assert!(board.possible_moves(Color::Red).contains(&next_move));
+    }
+
+    #[test]
+    fn alphabeta_takes_a_region() {
+        let mut board = random_board_where_red_can_score(123456789);
+        let current = board.score(Color::Red);
+        let mut player = AlphaBetaPlayer::new(Color::Red, 1, 60);
+        let mut next = player.play(board);
+        assert!(current < next.score(Color::Red));
+    }
+
+    #[test]
+    fn greedy_move_takes_a_region() {
+        let mut board = random_board_where_red_can_score(987654321);
+        let current = board.score(Color::Red);
+        for lookahead in [false, true].iter() {
+            let mut player = GreedyPlayer::new(Color::Red, 42, 0, *lookahead);
+            let mut next = player.play(board.clone());
+            assert!(current < next.score(Color::Red));
+        }
+    }
+
+    // play a game and return the score of red (1 if red wins, 0.5 if draw).
+    // The first two moves of each player are random to diversify the games.
+    fn play_leveled_game(red: u32, blue: u32, seed: u32) -> f64 {
+        let mut board = Board::new(9);
+        let mut opening = RandomPlayer::new(Color::Red, seed, 2);
+        for _ in 0..2 {
+            opening.color = Color::Red;
+            board = opening.play(board);
+            opening.color = Color::Blue;
+            board = opening.play(board);
+        }
+        let mut red  = LeveledPlayer::new(Color::Red,  seed, 0, red,  60, 9);
+        let mut blue = LeveledPlayer::new(Color::Blue, seed, 1, blue, 60, 9);
+        while !board.is_gameover() {
+            if board.can_move(Color::Red) {
+                board = red.play(board);
+            }
+            if board.can_move(Color::Blue) {
+                board = blue.play(board);
+            }
+        }
+        let red_score  = board.score(Color::Red);
+        let blue_score = board.score(Color::Blue);
+        if blue_score < red_score {
+            1.0
+        } else if red_score < blue_score {
+            0.0
+        } else {
+            0.5
+        }
+    }
+
+    #[test]
+    fn leveled_players_finish_a_game() {
+        for level in MIN_LEVEL..=MAX_LEVEL {
+            let strength = STRENGTH_LEVELS[(level - MIN_LEVEL) as usize];
+            if strength.playouts == 0 && strength.depth <= 1 {
+                play_leveled_game(level, level, level);
+            }
+        }
+    }
+
+    // Plays each level against the next one and prints the estimated Elo gap.
+    // It takes a long time. Run with
+    //     $ cargo test --release calibrate_levels -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn calibrate_levels() {
+        let games = 400;
+        for level in MIN_LEVEL..MAX_LEVEL {
+            let mut score = 0.0;
+            for game in 0..games {
+                // alternate colors in the same opening
+                score += if game % 2 == 0 {
+                    1.0 - play_leveled_game(level, level + 1, game / 2)
+                } else {
+                    play_leveled_game(level + 1, level, game / 2)
+                };
+            }
+            let rate = score / games as f64;
+            let elo = -400.0 * f64::log10(1.0 / rate.clamp(0.01, 0.99) - 1.0);
+            println!("level {:2} vs {:2}: {:.1}/{} (Elo gap = {:.0})",
+                     level + 1, level, score, games, elo);
+        }
+    }
+
+    #[test]
+    fn zobrist_hash() {
+        let mut board = Board::new(9);
+        let initial = board.zobrist_hash();
+        assert_ne!(initial, Board::new(8).zobrist_hash());
+
+        let red  = Move(Coord::new(0, 0), Coord::new(1, 1), Coord::new(1, 2));
+        let blue = Move(Coord::new(0, 8), Coord::new(1, 7), Coord::new(1, 6));
+        board.apply_move(red, Color::Red);
+        let after_red = board.zobrist_hash();
+        assert_ne!(after_red, initial);
+        board.apply_move(blue, Color::Blue);
+
+        // the hash does not depend on the order of the moves
+        let mut other = Board::new(9);
+        other.apply_move(blue, Color::Blue);
+        other.apply_move(red,  Color::Red);
+        assert_eq!(other.zobrist_hash(), board.zobrist_hash());
+        assert_ne!(other.zobrist_hash(), after_red);
+
+        // the same move in a symmetric position
+        let mut board = Board::new(9);
+        board.apply_move(red, Color::Red);
+        for symmetry in 0..NUM_SYMMETRIES {
+            let mut transformed = Board::new(9);
+            transformed.apply_move(red.transformed(symmetry, 9), Color::Red);
+            assert_eq!(transformed.zobrist_hash(), board.symmetric_hash(symmetry));
+            assert_eq!(transformed.canonical_hash().0, board.canonical_hash().0);
+        }
+    }
+
+    #[test]
+    fn num_moves() {
+        let mut board = Board::new(9);
+        assert_eq!(board.num_moves(), 0);
+        board.apply_move(Move(Coord::new(0, 0), Coord::new(1, 1), Coord::new(1, 2)), Color::Red);
+        assert_eq!(board.num_moves(), 1);
+        board.apply_move(Move(Coord::new(0, 8), Coord::new(1, 7), Coord::new(1, 6)), Color::Blue);
+        assert_eq!(board.num_moves(), 2);
+    }
+
+    #[test]
+    fn policy_index_roundtrip() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(42);
+        let mut board = Board::new(7);
+        let mut turn = Color::Red;
+        while !board.is_gameover() {
+            for m in board.possible_moves(turn) {
+                let idx = m.policy_index(7).unwrap();
+                assert!(idx < policy_size(7));
+                assert_eq!(Move::from_policy_index(idx, 7), Some(m));
+            }
+            if let Some(m) = board.possible_moves(turn).choose(&mut rng) {
+                board.apply_move(*m, turn);
+            }
+            turn = opponent_of(turn);
+        }
+
+        let indices = (0..policy_size(7))
+            .filter_map(|i| Move::from_policy_index(i, 7).map(|m| (i, m)))
+            .collect::<Vec<_>>();
+        assert!(indices.iter().all(|(i, m)| m.policy_index(7) == Some(*i)));
+        assert!(Move::from_policy_index(0, 7).is_some());        // a1-b2-c2
+        assert!(Move::from_policy_index(2, 7).is_none());        // a1 -> (-1, 1)
+        assert!("a1-b2-c3".parse::<Move>().unwrap().policy_index(7).is_none());
+    }
+
+    #[test]
+    fn feature_planes() {
+        let mut board = Board::new(5);
+        board.apply_move("a1-b2-b3".parse().unwrap(), Color::Red);
+        let area   = 25;
+        let planes = board.to_planes(Color::Blue);
+        assert_eq!(planes.len(), NUM_PLANES * area);
+        let plane  = |i: usize| &planes[i * area..(i + 1) * area];
+        let at     = |x: usize, y: usize| x * 5 + y;
+
+        assert_eq!(plane(0).iter().sum::<f32>(), 2.0);               // blue stones
+        assert_eq!(plane(1).iter().sum::<f32>(), 4.0);               // red stones
+        assert_eq!(plane(1)[at(1, 2)], 1.0);
+        assert_eq!(plane(2 + 8 + 1)[at(0, 0)], 1.0);                 // red root a1 -> b2
+        assert_eq!(plane(2 + 8 + 5)[at(1, 1)], 1.0);                 // red root b2 -> a1
+        assert_eq!(plane(2 + 8 + 2)[at(1, 1)], 1.0);                 // red root b2 -> b3
+        assert_eq!(planes[2 * area..10 * area].iter().sum::<f32>(), 0.0);
+        assert_eq!(plane(18)[at(0, 4)] + plane(18)[at(4, 0)], 2.0);
+        assert_eq!(plane(18).iter().sum::<f32>(), 2.0);
+        assert_eq!(plane(27).iter().sum::<f32>(), 16.0);
+
+        // no region is separated yet
+        for i in 19..27 {
+            assert!(plane(i).iter().enumerate().all(|(i, v)|
+                *v == if i / 5 < 4 && i % 5 < 4 {1.0} else {0.0}));
+        }
+
+        // region ids in the middle of a game, where Red can make a new region
+        let mut board = random_board_where_red_can_score(42);
+        let planes = board.to_planes(Color::Red);
+        let max_id = planes[19 * 81..23 * 81].iter().cloned().fold(0.0, f32::max);
+        assert_eq!(max_id as usize, board.red.region_sizes().len());
+    }
+
+    #[test]
+    fn move_text_roundtrip() {
+        let m = Move(Coord::new(0, 8), Coord::new(1, 7), Coord::new(1, 6));
+        assert_eq!(m.to_string(), "a9-b8-b7");
+        assert_eq!("A9-b8-b7".parse::<Move>(), Ok(m));
+        assert!("a9-b8".parse::<Move>().is_err());
+        assert!("a0-b8-b7".parse::<Move>().is_err());
+        assert!("9a-b8-b7".parse::<Move>().is_err());
+    }
+
+    #[test]
+    fn softmax_sampling() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(42);
+        let values = [0.0, 1.0, 0.5];
+        assert_eq!(super::sample_softmax(&values, 0.0, &mut rng), 1);
+
+        let mut counts = [0; 3];
+        for _ in 0..3000 {
+            counts[super::sample_softmax(&values, 1.0, &mut rng)] += 1;
+        }
+        // exp(0) : exp(1) : exp(0.5) ~ 1 : 2.7 : 1.6
+        assert!(counts[0] < counts[2] && counts[2] < counts[1]);
+
+        // all zero weights, as in ln(0) for moves that never won
+        let values = [f64::NEG_INFINITY; 3];
+        let mut counts = [0; 3];
+        for _ in 0..3000 {
+            counts[super::sample_softmax(&values, 1.0, &mut rng)] += 1;
+        }
+        assert!(counts.iter().all(|c| 800 < *c));
+    }
+
+    #[test]
+    fn temperature_diversifies_moves() {
+        let mut moves = Vec::new();
+        for seed in 0..8 {
+            let mut player = UCTMonteCarlo::new(Color::Red, seed, 0, 60, 2.0, 3, 9);
+            player.max_playouts = 100;
+            player.set_temperature(1.0, 1);
+            let board = player.play(Board::new(9));
+            if !moves.contains(&board.grids) {
+                moves.push(board.grids);
+            }
+        }
+        assert!(1 < moves.len());
+
+        let mut moves = Vec::new();
+        for seed in 0..8 {
+            let mut player = NaiveMonteCarlo::new(Color::Red, seed, 0, 0);
+            player.time_limit = Duration::from_millis(20);
+            player.set_temperature(1.0, 1);
+            let board = player.play(Board::new(9));
+            if !moves.contains(&board.grids) {
+                moves.push(board.grids);
+            }
+        }
+        assert!(1 < moves.len());
+    }
+
+    #[test]
+    fn decided_game_keeps_its_score() {
+        // with these seeds, the games are decided one turn before the end
+        for seed in [19, 75, 102].iter() {
+            let mut rng = rand::rngs::StdRng::seed_from_u64(*seed);
+            let mut board = Board::new(7);
+            assert!(!board.is_decided());
+
+            let mut decided = None;
+            while !board.is_gameover() {
+                if decided.is_none() && board.is_decided() {
+                    decided = Some((board.score(Color::Red), board.score(Color::Blue)));
+                }
+                for turn in [Color::Red, Color::Blue].iter() {
+                    let moves = board.possible_moves(*turn);
+                    if !moves.is_empty() {
+                        board.apply_move(moves[rng.gen_range(0, moves.len())], *turn);
+                    }
+                }
+            }
+            assert!(board.is_decided());
+            assert_eq!(decided, Some((board.score(Color::Red), board.score(Color::Blue))));
+        }
+    }
+
+    #[test]
+    fn monte_carlo_resigns() {
+        let board = Board::new(9);
+        let mut player = NaiveMonteCarlo::new(Color::Red, 42, 0, 1);
+        player.time_limit = Duration::from_millis(20);
+        player.set_resign_threshold(1.1);
+        assert_eq!(player.play(board.clone()), board);
+        assert!(player.has_resigned());
+        // the engine can be reused after it resigned
+        player.set_resign_threshold(0.0);
+        assert_eq!(player.play(board.clone()).num_moves(), 1);
+        assert!(!player.has_resigned());
+
+        let mut player = UCTMonteCarlo::new(Color::Red, 42, 0, 60, 2.0, 3, 9);
+        player.max_playouts = 100;
+        player.set_resign_threshold(1.1);
+        assert_eq!(player.play(board.clone()), board);
+        assert!(player.has_resigned());
+        player.set_resign_threshold(0.0);
+        assert_eq!(player.play(board.clone()).num_moves(), 1);
+        assert!(!player.has_resigned());
+    }
 }
@@ -0,0 +1,340 @@
+// A small policy/value network evaluated on CPU, without any dependency, so
+// that it runs in wasm as well as natively.
+//
+// The input is `Board::to_planes` of the side to move. The trunk is a sequence
+// of dense or 3x3 convolution layers, each followed by ReLU. On top of the
+// trunk, the policy head (dense) gives a logit for each index of
+// `Move::policy_index` and the value head (dense + tanh) gives the expected
+// result for the side to move, from -1 (lose) to 1 (win).
+//
+// Weight file format (all numbers are little-endian, f32 is IEEE 754):
+//
+//     magic    4 bytes, "SPNN"
+//     version  u32, 1
+//     width    u32, the board width
+//     planes   u32, the number of input planes (NUM_PLANES)
+//     layers   u32, the number of trunk layers
+//     for each trunk layer:
+//         kind     u32, 0 (dense) or 1 (3x3 convolution)
+//         outputs  u32, the output size (dense) or output channels (convolution)
+//         weights  f32 x outputs x inputs             (dense)
+//                  f32 x outputs x channels x 3 x 3   (convolution)
+//         biases   f32 x outputs
+//     policy head:
+//         weights  f32 x policy_size(width) x inputs
+//         biases   f32 x policy_size(width)
+//     value head:
+//         weights  f32 x inputs
+//         bias     f32
+//
+// Weights are stored in row-major order, i.e. the input index changes first.
+// Planes and convolution channels are laid out as [channel][x][y], the same as
+// `Board::to_planes`, and a 3x3 kernel as [dx + 1][dy + 1] where the output at
+// (x, y) reads the input at (x + dx, y + dy). A convolution pads the border
+// with zeros and keeps the size of the board, so it can only follow the input
+// or another convolution.
+// The number of inputs of a dense layer is the size of the previous output.
+
+use crate::{Board, Color, Move, NUM_PLANES, policy_size};
+
+use rand::prelude::*;
+
+const MAGIC:   &[u8; 4] = b"SPNN";
+const VERSION: u32      = 1;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Layer {
+    Dense{inputs: usize, outputs: usize, weights: Vec<f32>, biases: Vec<f32>},
+    Conv{channels: usize, outputs: usize, weights: Vec<f32>, biases: Vec<f32>},
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Network {
+    width:  usize,
+    trunk:  Vec<Layer>,
+    policy: Layer,
+    value:  Layer,
+}
+
+// reads the weight file from the head.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        if self.bytes.len() < n {
+            return Err("unexpected end of the weight file".to_string());
+        }
+        let (head, tail) = self.bytes.split_at(n);
+        self.bytes = tail;
+        Ok(head)
+    }
+    fn u32(&mut self) -> Result<u32, String> {
+        let b = self.take(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+    fn f32s(&mut self, n: usize) -> Result<Vec<f32>, String> {
+        Ok(self.take(product(&[n, 4])?)?.chunks(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect())
+    }
+    fn dense(&mut self, inputs: usize, outputs: usize) -> Result<Layer, String> {
+        let weights = self.f32s(product(&[inputs, outputs])?)?;
+        let biases  = self.f32s(outputs)?;
+        Ok(Layer::Dense{inputs, outputs, weights, biases})
+    }
+}
+
+// the size of a layer. A broken header can make it overflow.
+fn product(values: &[usize]) -> Result<usize, String> {
+    values.iter().try_fold(1usize, |acc, v| acc.checked_mul(*v))
+        .ok_or_else(|| "too large layer".to_string())
+}
+
+fn write_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+fn write_f32s(out: &mut Vec<u8>, values: &[f32]) {
+    values.iter().for_each(|v| out.extend_from_slice(&v.to_le_bytes()));
+}
+
+impl Layer {
+    fn forward(&self, input: &[f32], width: usize) -> Vec<f32> {
+        match self {
+            Layer::Dense{inputs, outputs, weights, biases} => {
+                (0..*outputs).map(|o| {
+                    let row = &weights[o * inputs..(o + 1) * inputs];
+                    row.iter().zip(input.iter()).map(|(w, x)| w * x).sum::<f32>() + biases[o]
+                }).collect()
+            }
+            Layer::Conv{channels, outputs, weights, biases} => {
+                let w = width as isize;
+                let area = width * width;
+                let mut output = vec![0.0; outputs * area];
+                for o in 0..*outputs {
+                    for x in 0..w {
+                        for y in 0..w {
+                            let mut sum = biases[o];
+                            for c in 0..*channels {
+                                let kernel = &weights[(o * channels + c) * 9..][..9];
+                                for dx in -1..=1 {
+                                    for dy in -1..=1 {
+                                        let (u, v) = (x + dx, y + dy);
+                                        if u < 0 || v < 0 || w <= u || w <= v {
+                                            continue;
+                                        }
+                                        sum += kernel[((dx + 1) * 3 + dy + 1) as usize] *
+                                               input[c * area + (u * w + v) as usize];
+                                    }
+                                }
+                            }
+                            output[o * area + (x * w + y) as usize] = sum;
+                        }
+                    }
+                }
+                output
+            }
+        }
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        match self {
+            Layer::Dense{weights, biases, ..} | Layer::Conv{weights, biases, ..} => {
+                write_f32s(out, weights);
+                write_f32s(out, biases);
+            }
+        }
+    }
+}
+
+impl Network {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Network, String> {
+        let mut reader = Reader{bytes};
+        if reader.take(4)? != MAGIC {
+            return Err("not a weight file".to_string());
+        }
+        let version = reader.u32()?;
+        if version != VERSION {
+            return Err(format!("unsupported version: {}", version));
+        }
+        let width = reader.u32()? as usize;
+        if !(4..20).contains(&width) {
+            return Err(format!("unsupported board size: {}", width));
+        }
+        let planes = reader.u32()? as usize;
+        if planes != NUM_PLANES {
+            return Err(format!("expected {} input planes, but got {}", NUM_PLANES, planes));
+        }
+
+        let area = width * width;
+        let mut channels = Some(NUM_PLANES); // None after a dense layer
+        let mut inputs   = NUM_PLANES * area;
+        let mut trunk    = Vec::new();
+        for _ in 0..reader.u32()? {
+            let kind    = reader.u32()?;
+            let outputs = reader.u32()? as usize;
+            let layer = match (kind, channels) {
+                (0, _) => {
+                    channels = None;
+                    reader.dense(inputs, outputs)?
+                }
+                (1, Some(c)) => {
+                    channels = Some(outputs);
+                    let weights = reader.f32s(product(&[outputs, c, 9])?)?;
+                    let biases  = reader.f32s(outputs)?;
+                    Layer::Conv{channels: c, outputs, weights, biases}
+                }
+                (1, None) => return Err("a convolution cannot follow a dense layer".to_string()),
+                _ => return Err(format!("unknown layer kind: {}", kind)),
+            };
+            inputs = match layer {
+                Layer::Dense{outputs, ..} => outputs,
+                Layer::Conv{outputs, ..}  => product(&[outputs, area])?,
+            };
+            trunk.push(layer);
+        }
+        let policy = reader.dense(inputs, policy_size(width))?;
+        let value  = reader.dense(inputs, 1)?;
+        if !reader.bytes.is_empty() {
+            return Err(format!("{} bytes left after the value head", reader.bytes.len()));
+        }
+        Ok(Network{width, trunk, policy, value})
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = MAGIC.to_vec();
+        write_u32(&mut out, VERSION);
+        write_u32(&mut out, self.width as u32);
+        write_u32(&mut out, NUM_PLANES as u32);
+        write_u32(&mut out, self.trunk.len() as u32);
+        for layer in self.trunk.iter() {
+            match layer {
+                Layer::Dense{outputs, ..} => {write_u32(&mut out, 0); write_u32(&mut out, *outputs as u32)}
+                Layer::Conv {outputs, ..} => {write_u32(&mut out, 1); write_u32(&mut out, *outputs as u32)}
+            }
+            layer.write(&mut out);
+        }
+        self.policy.write(&mut out);
+        self.value.write(&mut out);
+        out
+    }
+
+    // An untrained network with dense hidden layers of the given sizes,
+    // initialized with small random weights. It is useful as a starting point
+    // of training and for testing.
+    pub fn random(width: usize, hidden: &[usize], seed: u64) -> Network {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+        let mut dense = |inputs: usize, outputs: usize| {
+            let scale = 1.0 / f32::sqrt(inputs as f32);
+            let weights = (0..inputs * outputs).map(|_| rng.gen_range(-scale, scale)).collect();
+            Layer::Dense{inputs, outputs, weights, biases: vec![0.0; outputs]}
+        };
+        let mut inputs = NUM_PLANES * width * width;
+        let mut trunk  = Vec::new();
+        for outputs in hidden.iter() {
+            trunk.push(dense(inputs, *outputs));
+            inputs = *outputs;
+        }
+        let policy = dense(inputs, policy_size(width));
+        let value  = dense(inputs, 1);
+        Network{width, trunk, policy, value}
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    // The network can evaluate only the boards of its width.
+    pub fn check_width(&self, board_width: usize) -> Result<(), String> {
+        if board_width != self.width {
+            return Err(format!("the network is for {}x{} boards, not {}x{}",
+                               self.width, self.width, board_width, board_width));
+        }
+        Ok(())
+    }
+
+    // returns the prior probability of each possible move and the value for
+    // the side to move. The priors are the softmax of the policy logits over
+    // the possible moves, so they sum up to 1 unless the side has no move.
+    pub fn evaluate(&self, board: &Board, turn: Color) -> (Vec<(Move, f32)>, f32) {
+        assert_eq!(board.width as usize, self.width);
+        let mut x = board.to_planes(turn);
+        for layer in self.trunk.iter() {
+            x = layer.forward(&x, self.width);
+            x.iter_mut().for_each(|v| *v = v.max(0.0));
+        }
+        let logits = self.policy.forward(&x, self.width);
+        let value  = self.value.forward(&x, self.width)[0].tanh();
+
+        let moves  = board.possible_moves(turn);
+        let logits = moves.iter()
+            .map(|m| logits[m.policy_index(self.width).unwrap()]).collect::<Vec<_>>();
+        let max    = logits.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+        let exps   = logits.iter().map(|l| (l - max).exp()).collect::<Vec<_>>();
+        let total  = exps.iter().sum::<f32>();
+        let priors = moves.into_iter().zip(exps.iter()).map(|(m, e)| (m, e / total)).collect();
+        (priors, value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weight_file_roundtrip() {
+        let network = Network::random(5, &[16, 8], 42);
+        let bytes   = network.to_bytes();
+        assert_eq!(&bytes[..4], b"SPNN");
+        assert_eq!(Network::from_bytes(&bytes).unwrap(), network);
+
+        assert!(Network::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        let mut extra = bytes.clone();
+        extra.push(0);
+        assert!(Network::from_bytes(&extra).is_err());
+
+        // a convolution followed by a dense layer, written by hand
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(b"SPNN");
+        for v in [1, 4, NUM_PLANES as u32, 2, 1, 2].iter() {
+            write_u32(&mut bytes, *v);
+        }
+        write_f32s(&mut bytes, &vec![0.01; 2 * NUM_PLANES * 9 + 2]);
+        write_u32(&mut bytes, 0);
+        write_u32(&mut bytes, 3);
+        write_f32s(&mut bytes, &vec![0.01; 3 * 2 * 16 + 3]);
+        write_f32s(&mut bytes, &vec![0.0; policy_size(4) * 3 + policy_size(4)]);
+        write_f32s(&mut bytes, &[0.0, 0.0, 0.0, 0.5]);
+        let network = Network::from_bytes(&bytes).unwrap();
+        assert_eq!(network.to_bytes(), bytes);
+        assert!(network.check_width(4).is_ok());
+        assert!(network.check_width(5).is_err());
+
+        // a broken header with a huge layer, whose size overflows on wasm32
+        let mut huge = Vec::new();
+        huge.extend_from_slice(b"SPNN");
+        for v in [1, 4, NUM_PLANES as u32, 2, 1, u32::MAX, 1, u32::MAX].iter() {
+            write_u32(&mut huge, *v);
+        }
+        assert!(Network::from_bytes(&huge).is_err());
+
+        let (priors, value) = network.evaluate(&Board::new(4), Color::Red);
+        assert_eq!(priors.len(), Board::new(4).possible_moves(Color::Red).len());
+        // the policy head is zero, so the priors are uniform
+        assert!(priors.iter().all(|(_, p)| (p - priors[0].1).abs() < 1e-6));
+        assert!((value - 0.5f32.tanh()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn evaluate_possible_moves() {
+        let network = Network::random(5, &[16], 0);
+        let mut board = Board::new(5);
+        board.apply_move(board.possible_moves(Color::Red)[0], Color::Red);
+
+        let (priors, value) = network.evaluate(&board, Color::Blue);
+        assert_eq!(priors.iter().map(|(m, _)| *m).collect::<Vec<_>>(),
+                   board.possible_moves(Color::Blue));
+        assert!((priors.iter().map(|(_, p)| p).sum::<f32>() - 1.0).abs() < 1e-4);
+        assert!((-1.0..=1.0).contains(&value));
+    }
+}
@@ -0,0 +1,239 @@
+// A player that searches the game tree with PUCT, guided by a policy/value
+// network (see `network`), in the way of AlphaZero.
+//
+// Instead of a random playout, a leaf is evaluated by the network. The policy
+// gives the prior P of each move and the value is backed up to the root. At
+// each node, the search selects the child that maximizes
+//
+//     Q + c_puct * P * sqrt(N_parent) / (1 + N)
+//
+// where Q is the mean value of the child for the player who moves at the node
+// (0 if not visited yet) and N is the number of visits. The move with the most
+// visits is played. When the game is over, the final scores are used instead
+// of the network.
+
+use crate::{Board, Color, Move, opponent_of};
+use crate::engine::SearchInfo;
+use crate::instant::Instant;
+use crate::network::Network;
+
+use wasm_bindgen::prelude::*;
+
+use std::time::Duration;
+
+struct PUCTNode {
+    mv:        Option<Move>, // the move that results in this node. None if passed
+    turn:      Color,        // the player to move at this node
+    prior:     f32,
+    visits:    u32,
+    value_sum: f64,          // for the player who moved into this node
+    children:  Vec<usize>,   // indices in the tree
+}
+
+impl PUCTNode {
+    fn new(mv: Option<Move>, turn: Color, prior: f32) -> Self {
+        PUCTNode{mv, turn, prior, visits: 0, value_sum: 0.0, children: Vec::new()}
+    }
+
+    fn mean_value(&self) -> f64 {
+        if self.visits == 0 {
+            0.0
+        } else {
+            self.value_sum / self.visits as f64
+        }
+    }
+}
+
+#[wasm_bindgen]
+pub struct PUCTPlayer {
+    pub color:    Color,
+    network:      Network,
+    time_limit:   Duration,
+    max_playouts: u32,
+    c_puct:       f64,
+    last_search:  Option<SearchInfo>,
+    last_visits:  Vec<(Move, u32)>, // visits of each move in the last search
+}
+
+#[wasm_bindgen]
+impl PUCTPlayer {
+    // `weights` is the content of a weight file. `max_playouts` is the number
+    // of network evaluations per move; 0 means that only the time is limited.
+    // It fails if the network is not for `board_width`.
+    pub fn new(color: Color, weights: &[u8], timelimit: u32, max_playouts: u32, c_puct: f64,
+               board_width: usize) -> Result<PUCTPlayer, JsValue> {
+        let network = Network::from_bytes(weights)
+            .and_then(|network| network.check_width(board_width).map(|_| network))
+            .map_err(|e| JsValue::from_str(&e))?;
+        Ok(PUCTPlayer::with_network(color, network, timelimit, max_playouts, c_puct))
+    }
+
+    pub fn play(&mut self, mut board: Board) -> Board {
+        if let Some(next_move) = self.genmove(&board) {
+            board.apply_move(next_move, self.color);
+        }
+        board
+    }
+}
+
+impl PUCTPlayer {
+    pub fn with_network(color: Color, network: Network, timelimit: u32, max_playouts: u32,
+                        c_puct: f64) -> Self {
+        PUCTPlayer{
+            color,
+            network,
+            time_limit: Duration::from_secs(timelimit as u64),
+            max_playouts: if max_playouts == 0 {u32::MAX} else {max_playouts},
+            c_puct,
+            last_search: None,
+            last_visits: Vec::new(),
+        }
+    }
+
+    // adds the children of a leaf and returns its value for the player to move.
+    // The nodes do not keep boards to save memory, so the board of the leaf is
+    // passed.
+    fn expand(&self, tree: &mut Vec<PUCTNode>, leaf: usize, board: &mut Board) -> f64 {
+        let turn = tree[leaf].turn;
+        if board.is_gameover() {
+            let diff = board.score(turn) as i32 - board.score(opponent_of(turn)) as i32;
+            return diff.signum() as f64;
+        }
+
+        let (priors, value) = self.network.evaluate(board, turn);
+        let children = if priors.is_empty() {
+            vec![PUCTNode::new(None, opponent_of(turn), 1.0)]
+        } else {
+            priors.into_iter()
+                .map(|(mv, prior)| PUCTNode::new(Some(mv), opponent_of(turn), prior)).collect()
+        };
+        for child in children {
+            let index = tree.len();
+            tree[leaf].children.push(index);
+            tree.push(child);
+        }
+        value as f64
+    }
+
+    fn select(&self, tree: &[PUCTNode], node: usize) -> usize {
+        let sqrt_n = f64::sqrt(tree[node].visits as f64);
+        let score  = |child: &PUCTNode| child.mean_value() +
+            self.c_puct * child.prior as f64 * sqrt_n / (1.0 + child.visits as f64);
+        *tree[node].children.iter()
+            .max_by(|a, b| score(&tree[**a]).partial_cmp(&score(&tree[**b]))
+                .unwrap_or(std::cmp::Ordering::Less))
+            .unwrap()
+    }
+
+    pub(crate) fn genmove(&mut self, board: &Board) -> Option<Move> {
+        assert_eq!(board.width as usize, self.network.width());
+        if !board.can_move(self.color) {
+            return None;
+        }
+
+        let mut tree = vec![PUCTNode::new(None, self.color, 1.0)];
+        self.expand(&mut tree, 0, &mut board.clone());
+        tree[0].visits = 1;
+
+        let stop = Instant::now() + self.time_limit;
+        let mut playouts = 0;
+        while Instant::now() < stop && playouts < self.max_playouts {
+            let mut path = vec![0];
+            let mut node = 0;
+            let mut leaf_board = board.clone();
+            while !tree[node].children.is_empty() {
+                let turn = tree[node].turn;
+                node = self.select(&tree, node);
+                if let Some(mv) = tree[node].mv {
+                    leaf_board.apply_move(mv, turn);
+                }
+                path.push(node);
+            }
+            // the value for the player who moved into the leaf
+            let mut value = -self.expand(&mut tree, node, &mut leaf_board);
+            for node in path.iter().rev() {
+                tree[*node].visits    += 1;
+                tree[*node].value_sum += value;
+                value = -value;
+            }
+            playouts += 1;
+        }
+
+        let best = *tree[0].children.iter()
+            .max_by(|a, b| (tree[**a].visits, tree[**a].prior)
+                .partial_cmp(&(tree[**b].visits, tree[**b].prior))
+                .unwrap_or(std::cmp::Ordering::Less))
+            .unwrap();
+        self.last_search = Some(SearchInfo{
+            nodes:    playouts as u64,
+            win_rate: Some((tree[best].mean_value() + 1.0) / 2.0),
+            ..SearchInfo::default()
+        });
+        self.last_visits = tree[0].children.iter()
+            .filter_map(|c| tree[*c].mv.map(|m| (m, tree[*c].visits)))
+            .collect();
+        tree[best].mv
+    }
+
+    pub fn last_search(&self) -> Option<SearchInfo> {
+        self.last_search.clone()
+    }
+
+    // visits of each move in the last search, i.e. the improved policy.
+    pub fn last_visits(&self) -> &[(Move, u32)] {
+        &self.last_visits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RandomPlayer;
+
+    #[test]
+    fn puct_plays_valid_moves() {
+        let network = Network::random(5, &[16], 42);
+        let mut red  = PUCTPlayer::with_network(Color::Red,  network.clone(), 10, 50, 1.5);
+        let mut blue = PUCTPlayer::with_network(Color::Blue, network, 10, 50, 1.5);
+
+        let mut board = Board::new(5);
+        while !board.is_gameover() {
+            for player in [&mut red, &mut blue].iter_mut() {
+                let turn = player.color;
+                if let Some(next_move) = player.genmove(&board) {
+                    assert!(board.is_valid_move(next_move, turn));
+                    board.apply_move(next_move, turn);
+
+                    let info = player.last_search().unwrap();
+                    assert_eq!(info.nodes, 50);
+                    let total = player.last_visits().iter().map(|(_, n)| n).sum::<u32>();
+                    assert_eq!(total, 50);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn puct_beats_random() {
+        // the network knows nothing, but the search sees the end of the game
+        let network = Network::random(5, &[], 0);
+        let mut wins = 0;
+        for game in 0..6 {
+            let mut puct   = PUCTPlayer::with_network(Color::Red, network.clone(), 10, 300, 1.5);
+            let mut random = RandomPlayer::new(Color::Blue, game, 0);
+            let mut board  = Board::new(5);
+            while !board.is_gameover() {
+                if let Some(next_move) = puct.genmove(&board) {
+                    board.apply_move(next_move, Color::Red);
+                }
+                if let Some(next_move) = random.genmove(&board) {
+                    board.apply_move(next_move, Color::Blue);
+                }
+            }
+            if board.score(Color::Blue) < board.score(Color::Red) {
+                wins += 1;
+            }
+        }
+        assert!(4 <= wins, "{} wins", wins);
+    }
+}
@@ -1,61 +1,690 @@
 use wasm_bindgen::prelude::*;
 use std::rc::Rc;
 use std::cell::RefCell;
+use std::io::{self, Write};
+use std::borrow::Cow;
+use std::collections::HashMap;
 use gif::SetParameter;
 
-struct ToVecRefWriter {
-    inner: Rc<RefCell<Vec<u8>>>,
+use crate::{Board, Color, opponent_of};
+use crate::game_record::GameRecord;
+use crate::apng::{decode_png, encode_png, ApngEncoder};
+use crate::render::{self, Image, RenderOptions, Rgba, Theme};
+
+// The file is written to a sink shared with the GIF encoder, which takes the
+// writer but does not give it back.
+struct SharedWriter<W: Write> {
+    inner: Rc<RefCell<W>>,
 }
-impl std::io::Write for ToVecRefWriter {
-    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+impl<W: Write> Write for SharedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         self.inner.borrow_mut().write(buf)
     }
-    fn flush(&mut self) -> std::io::Result<()> {
+    fn flush(&mut self) -> io::Result<()> {
         self.inner.borrow_mut().flush()
     }
 }
 
+// The output of the recorder. `Apng` is lossless, without the 256 colors of
+// GIF. `ContactSheet` is a PNG that shows all the frames in a grid. The whole
+// sheet is made at the end, 4 bytes per pixel (about 1.4 MB per board image
+// at scale 1), so a smaller `scale` saves memory for long games. A sheet of
+// more than `render::MAX_PIXELS` pixels is an error. The frames of APNG and a
+// contact sheet must have the same size.
 #[wasm_bindgen]
-pub struct GameGifRecorder {
-    png_buffer: Vec<u8>,
-    gif_buffer: Rc<RefCell<Vec<u8>>>,
-    gif_encoder: Option<gif::Encoder<ToVecRefWriter>>,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordFormat {
+    Gif,
+    Apng,
+    ContactSheet,
 }
 
+// Options of a recording. The delays are in 1/100 seconds. `speed` and
+// `theme_palette` are used only for GIF.
+//
+// With `theme_palette`, all the frames share a fixed palette made from the
+// colors of `theme` and the blends between them (see `Palette`), and each
+// frame after the first contains only the rectangle that changed, with the
+// unchanged pixels transparent. Since a move changes only a few grids, this
+// makes the GIF far smaller. Otherwise, each frame is the whole image with
+// its own palette quantized at `speed`.
 #[wasm_bindgen]
-impl GameGifRecorder {
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GifOptions {
+    pub delay:         u16,  // delay of each frame
+    pub final_delay:   u16,  // delay of the last frame, to hold the final position
+    pub loops:         u16,  // the number of times the animation is played. 0 means forever
+    pub scale:         f64,  // size of the GIF relative to the frames
+    pub speed:         i32,  // speed of the color quantization, 1 (best) to 30 (fastest)
+    pub theme_palette: bool, // a fixed palette from the theme and the changed part only
+    pub format:        RecordFormat,
+    #[wasm_bindgen(skip)]
+    pub theme:         Theme, // used to render boards and to make the palette
+}
+
+#[wasm_bindgen]
+impl GifOptions {
     pub fn new() -> Self {
-        GameGifRecorder{
-            png_buffer: Vec::new(),
-            gif_buffer: Rc::new(RefCell::new(Vec::new())),
+        GifOptions{delay: 100, final_delay: 100, loops: 0, scale: 1.0, speed: 20,
+                   theme_palette: true, format: RecordFormat::Gif, theme: Theme::default()}
+    }
+
+    // sets the colors from text, e.g. "dark" or "light,background=#f0e0c0".
+    // See `Theme` for the format.
+    pub fn set_theme(&mut self, theme: &str) -> Result<(), JsValue> {
+        self.theme = theme.parse().map_err(|e: String| JsValue::from_str(&e))?;
+        Ok(())
+    }
+
+    // The board images scaled by `scale` must fit in a GIF, whose width and
+    // height are u16, and have at most `render::MAX_PIXELS` pixels.
+    pub fn check(&self) -> Result<(), String> {
+        let (width, height) = (render::IMAGE_WIDTH as f64, render::IMAGE_HEIGHT as f64);
+        let max = (u16::MAX as f64 / width.max(height))
+            .min((render::MAX_PIXELS as f64 / (width * height)).sqrt());
+        if !(self.scale.is_finite() && 0.0 < self.scale && self.scale <= max) {
+            return Err(format!("scale must be more than 0 and at most {:.1}: {}", max, self.scale));
+        }
+        Ok(())
+    }
+}
+
+// The number of blends between two colors of the theme is chosen so that the
+// palette fits in 255 colors. The last index is transparent.
+struct Palette {
+    colors: Vec<[u8; 3]>,
+    cache:  HashMap<[u8; 3], u8>, // the nearest color of each color seen
+}
+
+impl Palette {
+    fn from_theme(theme: &Theme) -> Self {
+        // the colors as they appear on the background
+        let over = |c: Rgba| -> [u8; 3] {
+            let a = c[3] as f64 / 255.0;
+            let mut rgb = [0; 3];
+            for (i, v) in rgb.iter_mut().enumerate() {
+                *v = (c[i] as f64 * a + theme.background[i] as f64 * (1.0 - a)).round() as u8;
+            }
+            rgb
+        };
+        let mut base: Vec<[u8; 3]> = Vec::new();
+        let all = [theme.background, theme.grid, theme.text, theme.fill[0], theme.fill[1],
+                   theme.stroke[0], theme.stroke[1], theme.guide[0], theme.guide[1]];
+        for color in all.iter().map(|c| over(*c)) {
+            if !base.contains(&color) {
+                base.push(color);
+            }
+        }
+
+        let pairs = base.len() * (base.len() - 1) / 2;
+        let steps = (255 - base.len()).checked_div(pairs).unwrap_or(0) + 1;
+        let mut colors = base.clone();
+        for i in 0..base.len() {
+            for j in (i + 1)..base.len() {
+                for k in 1..steps {
+                    let t = k as f64 / steps as f64;
+                    let mut rgb = [0; 3];
+                    for (c, v) in rgb.iter_mut().enumerate() {
+                        *v = (base[i][c] as f64 * (1.0 - t) + base[j][c] as f64 * t).round() as u8;
+                    }
+                    colors.push(rgb);
+                }
+            }
+        }
+        Palette{colors, cache: HashMap::new()}
+    }
+
+    fn transparent(&self) -> u8 {
+        self.colors.len() as u8
+    }
+
+    fn index(&mut self, rgb: [u8; 3]) -> u8 {
+        let colors = &self.colors;
+        *self.cache.entry(rgb).or_insert_with(|| {
+            let distance = |c: &[u8; 3]| c.iter().zip(rgb.iter())
+                .map(|(a, b)| (*a as i32 - *b as i32).pow(2)).sum::<i32>();
+            (0..colors.len()).min_by_key(|i| distance(&colors[*i])).unwrap() as u8
+        })
+    }
+
+    // the global color table, with the transparent color at the end
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = self.colors.iter().flatten().cloned().collect();
+        bytes.extend_from_slice(&self.colors[0]);
+        bytes
+    }
+}
+
+impl Default for GifOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// a contact sheet is about square
+fn sheet_columns(frames: usize) -> usize {
+    (frames as f64).sqrt().ceil() as usize
+}
+
+fn title(color: Color) -> &'static str {
+    match color {
+        Color::Red  => "Red",
+        Color::Blue => "Blue",
+    }
+}
+
+// A recorder that writes to any sink, e.g. a file.
+//
+// A GIF is written frame by frame as the frames are added, except for the
+// last frame, whose delay is known only when the recording finishes. An APNG
+// and a contact sheet need all the frames, so they are kept compressed and
+// written by `finish`.
+pub struct Recorder<W: Write> {
+    options:     GifOptions,
+    sink:        Rc<RefCell<W>>,
+    gif_encoder: Option<gif::Encoder<SharedWriter<W>>>,
+    gif_size:    (u16, u16),      // of the GIF being written by gif_encoder
+    last_frame:  Option<gif::Frame<'static>>, // written when the next frame comes
+    palette:     Palette,
+    previous:    Option<Vec<u8>>, // palette indices of the previous frame
+    apng:        ApngEncoder,
+    sheet:       Vec<Vec<u8>>,    // frames of the contact sheet in PNG
+    sheet_size:  (u32, u32),      // of the frames in the sheet
+}
+
+impl<W: Write> Recorder<W> {
+    // It fails if the options are invalid, see `GifOptions::check`.
+    pub fn new(sink: W, options: GifOptions) -> io::Result<Self> {
+        options.check().map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        Ok(Recorder{
+            options,
+            sink: Rc::new(RefCell::new(sink)),
             gif_encoder: None,
+            gif_size: (0, 0),
+            last_frame: None,
+            palette: Palette::from_theme(&options.theme),
+            previous: None,
+            apng: ApngEncoder::new(options.loops as u32),
+            sheet: Vec::new(),
+            sheet_size: (0, 0),
+        })
+    }
+
+    pub fn options(&self) -> &GifOptions {
+        &self.options
+    }
+
+    pub fn add_image(&mut self, image: Image) -> io::Result<()> {
+        let image = image.scaled(self.options.scale);
+        match self.options.format {
+            RecordFormat::Gif          => self.add_gif_frame(image)?,
+            RecordFormat::Apng         => self.apng.add_frame(&image, self.options.delay)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?,
+            RecordFormat::ContactSheet => {
+                if self.sheet.is_empty() {
+                    self.sheet_size = (image.width, image.height);
+                } else if self.sheet_size != (image.width, image.height) {
+                    return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                        format!("the frame is {}x{}, but the sheet is of {}x{} frames",
+                                image.width, image.height, self.sheet_size.0, self.sheet_size.1)));
+                }
+                // fail now rather than after the whole game
+                let count = self.sheet.len() + 1;
+                render::contact_sheet_size(count, sheet_columns(count), image.width, image.height)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+                self.sheet.push(encode_png(&image))
+            }
         }
+        Ok(())
+    }
+
+    // draws the board with the theme of the options and adds it as a frame.
+    pub fn add_board(&mut self, board: &Board, red_name: &str, blue_name: &str, message: &str,
+                     guide: bool) -> io::Result<()> {
+        let options = RenderOptions{
+            theme:     self.options.theme,
+            red_name:  red_name.to_string(),
+            blue_name: blue_name.to_string(),
+            message:   message.to_string(),
+            guide,
+        };
+        self.add_image(render::render(board, &options))
+    }
+
+    // adds a frame rendered with options. With `theme_palette`, the colors
+    // that are not in the palette of the recorder are replaced by the nearest.
+    pub fn add_rendered(&mut self, board: &Board, options: &RenderOptions) -> io::Result<()> {
+        self.add_image(render::render(board, options))
+    }
+
+    // adds every position of a game with the messages of the web page, and
+    // the final position with the result. A pass adds no frame.
+    pub fn add_record(&mut self, record: &GameRecord, guide: bool) -> io::Result<()> {
+        let invalid = |e: String| io::Error::new(io::ErrorKind::InvalidData, e);
+        let boards = record.replay().map_err(invalid)?;
+        let result = match (record.resigned, record.winner().map_err(invalid)?) {
+            (Some(color), _)    => format!("{} resigned. {} wins!", title(color), title(opponent_of(color))),
+            (None, Some(color)) => format!("{} wins!", title(color)),
+            (None, None)        => "draw!".to_string(),
+        };
+        self.add_board(&boards[0], &record.red, &record.blue, "Red's turn", guide)?;
+        for (ply, mv) in record.moves.iter().enumerate() {
+            if mv.is_some() {
+                let message = format!("{}'s turn", title(GameRecord::turn_of(ply + 1)));
+                self.add_board(&boards[ply + 1], &record.red, &record.blue, &message, guide)?;
+            }
+        }
+        self.add_board(boards.last().unwrap(), &record.red, &record.blue, &result, guide)
+    }
+
+    fn add_gif_frame(&mut self, mut image: Image) -> io::Result<()> {
+        if image.width > u16::MAX as u32 || image.height > u16::MAX as u32 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                format!("too large image for GIF: {}x{}", image.width, image.height)));
+        }
+        let (width, height) = (image.width as u16, image.height as u16);
+        if self.gif_encoder.is_none() {
+            let palette = if self.options.theme_palette {self.palette.to_bytes()} else {Vec::new()};
+            let mut encoder = gif::Encoder::new(
+                SharedWriter{inner: Rc::clone(&self.sink)}, width, height, &palette)?;
+            match self.options.loops {
+                0 => encoder.set(gif::Repeat::Infinite)?,
+                1 => {} // without the loop extension, a GIF is played once
+                n => encoder.set(gif::Repeat::Finite(n - 1))?,
+            }
+            self.gif_encoder = Some(encoder);
+            self.gif_size    = (width, height);
+        } else if self.gif_size != (width, height) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                format!("the frame is {}x{}, but the GIF is {}x{}",
+                        width, height, self.gif_size.0, self.gif_size.1)));
+        }
+        let mut frame = if self.options.theme_palette {
+            self.indexed_frame(&image)
+        } else {
+            let speed = self.options.speed.clamp(1, 30);
+            gif::Frame::from_rgba_speed(width, height, &mut image.pixels, speed)
+        };
+        frame.delay = self.options.delay;
+
+        // the last frame is held until we know whether it is the final one
+        if let Some(last) = self.last_frame.replace(frame) {
+            self.gif_encoder.as_mut().unwrap().write_frame(&last)?;
+        }
+        Ok(())
+    }
+
+    // a frame in the palette that covers the pixels changed from the previous
+    // frame. The unchanged pixels in it are transparent.
+    fn indexed_frame(&mut self, image: &Image) -> gif::Frame<'static> {
+        let palette = &mut self.palette;
+        let indices: Vec<u8> = image.pixels.chunks(4)
+            .map(|p| palette.index([p[0], p[1], p[2]])).collect();
+        let (width, height) = (image.width as usize, image.height as usize);
+
+        let previous = match self.previous.replace(indices.clone()) {
+            Some(previous) => previous,
+            _ => return gif::Frame{width: width as u16, height: height as u16,
+                                   buffer: Cow::Owned(indices), ..gif::Frame::default()},
+        };
+        let transparent = self.palette.transparent();
+        let changed = |i: usize| previous[i] != indices[i];
+        let (mut x0, mut y0, mut x1, mut y1) = (width, height, 0, 0);
+        for y in 0..height {
+            for x in 0..width {
+                if changed(y * width + x) {
+                    x0 = x0.min(x);
+                    y0 = y0.min(y);
+                    x1 = x1.max(x + 1);
+                    y1 = y1.max(y + 1);
+                }
+            }
+        }
+        if x1 == 0 { // nothing changed. a transparent pixel keeps the delay
+            return gif::Frame{width: 1, height: 1, transparent: Some(transparent),
+                              buffer: Cow::Owned(vec![transparent]), ..gif::Frame::default()};
+        }
+        let mut buffer = Vec::with_capacity((x1 - x0) * (y1 - y0));
+        for y in y0..y1 {
+            buffer.extend((x0..x1).map(|x| {
+                let i = y * width + x;
+                if changed(i) {indices[i]} else {transparent}
+            }));
+        }
+        gif::Frame{left: x0 as u16, top: y0 as u16, width: (x1 - x0) as u16,
+                   height: (y1 - y0) as u16, transparent: Some(transparent),
+                   buffer: Cow::Owned(buffer), ..gif::Frame::default()}
+    }
+
+    // writes the rest of the file to the sink. Then another recording can be
+    // started, into the same sink or a new one (see `reset`).
+    pub fn finish(&mut self) -> io::Result<()> {
+        match self.options.format {
+            RecordFormat::Gif => {
+                if let Some(mut last) = self.last_frame.take() {
+                    last.delay = self.options.final_delay;
+                    self.gif_encoder.as_mut().unwrap().write_frame(&last)?;
+                }
+                self.gif_encoder = None; // drop gif_encoder to write the trailer
+            }
+            RecordFormat::Apng => {
+                self.apng.set_last_delay(self.options.final_delay);
+                let apng = self.apng.finish();
+                self.sink.borrow_mut().write_all(&apng)?;
+            }
+            RecordFormat::ContactSheet => {
+                let frames = std::mem::take(&mut self.sheet);
+                let sheet = render::contact_sheet(frames.iter().map(|png| decode_png(png)),
+                    sheet_columns(frames.len()), self.options.theme.background)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+                self.sink.borrow_mut().write_all(&encode_png(&sheet))?;
+            }
+        }
+        self.previous = None;
+        self.sink.borrow_mut().flush()
+    }
+
+    // discards the frames that are not written yet and starts over with a new
+    // sink. It returns the old sink.
+    pub fn reset(&mut self, sink: W) -> W {
+        self.last_frame = None;
+        self.gif_encoder = None; // writes the trailer of an unfinished GIF to the old sink
+        self.previous = None;
+        self.apng = ApngEncoder::new(self.options.loops as u32);
+        self.sheet.clear();
+        std::mem::replace(&mut *self.sink.borrow_mut(), sink)
+    }
+
+    pub fn into_inner(mut self) -> W {
+        self.gif_encoder = None;
+        match Rc::try_unwrap(self.sink) {
+            Ok(sink) => sink.into_inner(),
+            Err(_)   => unreachable!(), // gif_encoder is the only other owner
+        }
+    }
+}
+
+// The recorder for the browser, that keeps the file in memory.
+#[wasm_bindgen]
+pub struct GameGifRecorder {
+    recorder: Recorder<Vec<u8>>,
+}
+
+#[wasm_bindgen]
+impl GameGifRecorder {
+    pub fn new() -> Self {
+        GameGifRecorder{recorder: Recorder::new(Vec::new(), GifOptions::new()).unwrap()}
+    }
+    // It fails if the options are invalid, e.g. `scale` is too large.
+    pub fn with_options(options: GifOptions) -> Result<GameGifRecorder, JsValue> {
+        let recorder = Recorder::new(Vec::new(), options).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        Ok(GameGifRecorder{recorder})
     }
     pub fn add_frame(&mut self, img: String) {
         let content: Vec<u8> = base64::decode(
             img.trim_start_matches("data:image/png;base64,")).unwrap();
         let decoder = png::Decoder::new(&content[..]);
         let (info, mut reader) = decoder.read_info().unwrap();
-        self.png_buffer.resize(info.buffer_size(), 0);
-        reader.next_frame(&mut self.png_buffer).unwrap();
+        let mut pixels = vec![0; info.buffer_size()];
+        reader.next_frame(&mut pixels).unwrap();
 
-        if self.gif_encoder.is_none() {
-            self.gif_encoder = Some(gif::Encoder::new(
-                ToVecRefWriter{inner: Rc::clone(&self.gif_buffer)},
-                info.width as u16,
-                info.height as u16,
-                &[]).unwrap());
-            self.gif_encoder.as_mut().unwrap().set(gif::Repeat::Infinite).unwrap();
+        self.recorder.add_image(Image{width: info.width, height: info.height, pixels}).unwrap();
+    }
+
+    // draws the board in Rust (see `render`) and adds it as a frame, without
+    // going through the canvas.
+    pub fn add_board(&mut self, board: &Board, red_name: &str, blue_name: &str, message: &str,
+                     guide: bool) {
+        self.recorder.add_board(board, red_name, blue_name, message, guide).unwrap();
+    }
+
+    // finishes the recording and returns the file (an Uint8Array in JS).
+    // After that, the recorder is ready to record another game.
+    pub fn finish(&mut self) -> Vec<u8> {
+        self.recorder.finish().unwrap();
+        self.recorder.reset(Vec::new())
+    }
+
+    // the animation of a game record, e.g. an archived game, without playing
+    // it again. The frames that are not finished yet are discarded.
+    pub fn record_game(&mut self, record: &str, guide: bool) -> Result<Vec<u8>, JsValue> {
+        let record = record.parse::<GameRecord>().map_err(|e| JsValue::from_str(&e))?;
+        self.reset();
+        self.recorder.add_record(&record, guide).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        Ok(self.finish())
+    }
+
+    // discards the frames and starts over
+    pub fn reset(&mut self) {
+        self.recorder.reset(Vec::new());
+    }
+
+    // for a Blob or a data URL
+    pub fn mime_type(&self) -> String {
+        match self.recorder.options().format {
+            RecordFormat::Gif => "image/gif".to_string(),
+            RecordFormat::Apng | RecordFormat::ContactSheet => "image/png".to_string(),
+        }
+    }
+}
+
+impl Default for GameGifRecorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GameGifRecorder {
+    pub fn add_rendered(&mut self, board: &Board, options: &RenderOptions) {
+        self.recorder.add_rendered(board, options).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Color, Move};
+
+    fn decode(gif: &[u8]) -> (gif::Reader<&[u8]>, Vec<u16>) {
+        let mut decoder = gif::Decoder::new(gif).read_info().unwrap();
+        let mut delays = Vec::new();
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            delays.push(frame.delay);
         }
-        let mut frame = gif::Frame::from_rgba_speed(
-            info.width as u16, info.height as u16, &mut self.png_buffer, 20);
-        frame.delay = 100;
+        (decoder, delays)
+    }
 
-        self.gif_encoder.as_mut().unwrap().write_frame(&frame).unwrap();
+    #[test]
+    fn record_boards_natively() {
+        let mut recorder = GameGifRecorder::new();
+        let mut board = Board::new(5);
+        recorder.add_board(&board, "red", "blue", "Red's turn", false);
+        board.apply_move("a1-b2-b3".parse::<Move>().unwrap(), Color::Red);
+        recorder.add_board(&board, "red", "blue", "Blue's turn", true);
+
+        let gif = recorder.finish();
+        let (decoder, delays) = decode(&gif);
+        assert_eq!((decoder.width() as u32, decoder.height() as u32),
+                   (render::IMAGE_WIDTH, render::IMAGE_HEIGHT));
+        assert_eq!(delays, vec![100, 100]);
     }
 
-    pub fn dump(&mut self) -> String {
-        self.gif_encoder = None; // drop gif_encoder
-        base64::encode(&*self.gif_buffer.borrow())
+    #[test]
+    fn record_with_options() {
+        let options = GifOptions{delay: 50, final_delay: 300, loops: 1, scale: 0.5,
+                                 ..GifOptions::new()};
+        let mut recorder = GameGifRecorder::with_options(options).unwrap();
+        let board = Board::new(5);
+        for _ in 0..3 {
+            recorder.add_board(&board, "red", "blue", "", false);
+        }
+        let small = recorder.finish();
+        let (decoder, delays) = decode(&small);
+        assert_eq!((decoder.width() as u32, decoder.height() as u32),
+                   (render::IMAGE_WIDTH / 2, render::IMAGE_HEIGHT / 2));
+        assert_eq!(delays, vec![50, 50, 300]);
+
+        let mut recorder = GameGifRecorder::new();
+        for _ in 0..3 {
+            recorder.add_board(&board, "red", "blue", "", false);
+        }
+        assert!(small.len() < recorder.finish().len());
+
+        // the size of a GIF is u16
+        for scale in [0.0, -1.0, f64::NAN, f64::INFINITY, 30.0, 200.0].iter() {
+            let options = GifOptions{scale: *scale, ..GifOptions::new()};
+            let err = Recorder::new(Vec::new(), options).err().unwrap();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        }
+        let mut recorder = Recorder::new(Vec::new(), GifOptions::new()).unwrap();
+        let err = recorder.add_image(Image::new(70000, 1, [0; 4])).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn frame_differencing() {
+        let mut boards = vec![Board::new(7)];
+        for ply in 0..6 {
+            let mut board = boards.last().unwrap().clone();
+            let turn = if ply % 2 == 0 {Color::Red} else {Color::Blue};
+            let mv = board.possible_moves(turn)[0];
+            board.apply_move(mv, turn);
+            boards.push(board);
+        }
+        let record = |options: GifOptions| {
+            let mut recorder = GameGifRecorder::with_options(options).unwrap();
+            for board in boards.iter() {
+                recorder.add_board(board, "red", "blue", "", false);
+            }
+            recorder.add_board(boards.last().unwrap(), "red", "blue", "", false); // no change
+            recorder.finish()
+        };
+        let compact = record(GifOptions::new());
+        let full    = record(GifOptions{theme_palette: false, ..GifOptions::new()});
+        assert!(compact.len() * 4 < full.len(), "{} vs {}", compact.len(), full.len());
+
+        // put the frames together and compare them with the boards
+        let mut palette = Palette::from_theme(&Theme::default());
+        let mut decoder = gif::Decoder::new(&compact[..]).read_info().unwrap();
+        assert_eq!(decoder.global_palette().unwrap()[..palette.colors.len() * 3],
+                   palette.to_bytes()[..palette.colors.len() * 3]);
+        let width = render::IMAGE_WIDTH as usize;
+        let mut canvas = vec![0u8; width * render::IMAGE_HEIGHT as usize];
+        let mut frame_sizes = Vec::new();
+        for board in boards.iter().chain(boards.last()) {
+            let frame = decoder.read_next_frame().unwrap().unwrap();
+            frame_sizes.push(frame.buffer.len());
+            for (i, index) in frame.buffer.iter().enumerate() {
+                if Some(*index) != frame.transparent {
+                    let (x, y) = (frame.left as usize + i % frame.width as usize,
+                                  frame.top  as usize + i / frame.width as usize);
+                    canvas[y * width + x] = *index;
+                }
+            }
+            let options = RenderOptions{red_name: "red".to_string(), blue_name: "blue".to_string(),
+                                        ..RenderOptions::default()};
+            let expected: Vec<u8> = render::render(board, &options).pixels.chunks(4)
+                .map(|p| palette.index([p[0], p[1], p[2]])).collect();
+            assert!(canvas == expected);
+        }
+        assert!(decoder.read_next_frame().unwrap().is_none());
+        assert_eq!(frame_sizes[0], canvas.len());
+        assert!(frame_sizes[1..].iter().all(|n| *n < canvas.len()));
+        assert_eq!(*frame_sizes.last().unwrap(), 1);
+    }
+
+    #[test]
+    fn record_apng_and_contact_sheet() {
+        let board = Board::new(5);
+        let record = |format: RecordFormat| {
+            let mut recorder = GameGifRecorder::with_options(
+                GifOptions{format, scale: 0.5, ..GifOptions::new()}).unwrap();
+            for message in ["a", "b", "c", "d", "e"].iter() {
+                recorder.add_board(&board, "red", "blue", message, false);
+            }
+            assert_eq!(recorder.mime_type(), "image/png");
+            recorder.finish()
+        };
+
+        let apng = record(RecordFormat::Apng);
+        let (info, reader) = png::Decoder::new(&apng[..]).read_info().unwrap();
+        assert_eq!((info.width, info.height), (render::IMAGE_WIDTH / 2, render::IMAGE_HEIGHT / 2));
+        assert_eq!(reader.info().animation_control().unwrap().num_frames, 5);
+
+        // 5 frames in 3 columns and 2 rows, with gaps
+        let sheet = record(RecordFormat::ContactSheet);
+        let (info, reader) = png::Decoder::new(&sheet[..]).read_info().unwrap();
+        assert_eq!((info.width, info.height),
+                   (3 * (render::IMAGE_WIDTH / 2 + 10) + 10, 2 * (render::IMAGE_HEIGHT / 2 + 10) + 10));
+        assert!(reader.info().animation_control().is_none());
+
+        // all the frames have the size of the first one
+        let formats = [(RecordFormat::Gif, true), (RecordFormat::Gif, false),
+                       (RecordFormat::Apng, true), (RecordFormat::ContactSheet, true)];
+        for (format, theme_palette) in formats.iter() {
+            let options = GifOptions{format: *format, theme_palette: *theme_palette, ..GifOptions::new()};
+            let mut recorder = Recorder::new(Vec::new(), options).unwrap();
+            recorder.add_image(Image::new(4, 4, [0; 4])).unwrap();
+            let err = recorder.add_image(Image::new(4, 5, [0; 4])).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        }
+
+        // the sheet is checked before it is allocated
+        assert!(render::contact_sheet_size(600, 25, render::IMAGE_WIDTH, render::IMAGE_HEIGHT).is_ok());
+        assert!(render::contact_sheet_size(900, 30, render::IMAGE_WIDTH, render::IMAGE_HEIGHT).is_err());
+    }
+
+    #[test]
+    fn stream_and_reset() {
+        let board = Board::new(5);
+        let mut recorder = Recorder::new(Vec::new(), GifOptions::new()).unwrap();
+        recorder.add_board(&board, "red", "blue", "a", false).unwrap();
+        recorder.add_board(&board, "red", "blue", "b", false).unwrap();
+        let written = recorder.sink.borrow().len();
+        assert!(0 < written); // the first frame is already in the sink
+
+        // an unfinished game is discarded and another game goes to a new sink
+        let old = recorder.reset(Vec::new());
+        assert!(written < old.len()); // with the trailer
+        for message in ["c", "d", "e"].iter() {
+            recorder.add_board(&board, "red", "blue", message, false).unwrap();
+        }
+        recorder.finish().unwrap();
+        let (_, delays) = decode(&recorder.into_inner());
+        assert_eq!(delays.len(), 3);
+
+        // the same in wasm, where finish returns the file
+        let mut recorder = GameGifRecorder::new();
+        for game in 1..3 {
+            for _ in 0..game {
+                recorder.add_board(&board, "red", "blue", "", false);
+            }
+            assert_eq!(decode(&recorder.finish()).1.len(), game);
+        }
+    }
+
+    #[test]
+    fn record_from_game_record() {
+        let mut record = GameRecord::new(5, "red".to_string(), "blue".to_string());
+        let mut board = Board::new(5);
+        for ply in 0..4 {
+            let turn = GameRecord::turn_of(ply);
+            let mv = board.possible_moves(turn)[0];
+            board.apply_move(mv, turn);
+            record.moves.push(Some(mv));
+        }
+        let options = GifOptions{format: RecordFormat::Apng, ..GifOptions::new()};
+        let mut recorder = Recorder::new(Vec::new(), options).unwrap();
+        recorder.add_record(&record, false).unwrap();
+        recorder.finish().unwrap();
+        let apng = recorder.into_inner();
+        let (_, reader) = png::Decoder::new(&apng[..]).read_info().unwrap();
+        // the initial position, 4 moves and the result
+        assert_eq!(reader.info().animation_control().unwrap().num_frames, 1 + 4 + 1);
+
+        record.moves.push(Some(record.moves[0].unwrap())); // illegal
+        let mut recorder = Recorder::new(Vec::new(), GifOptions::new()).unwrap();
+        let err = recorder.add_record(&record, false).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
     }
 }
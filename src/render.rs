@@ -0,0 +1,547 @@
+// Rasterize a board into an RGBA image without a canvas, so that images and
+// GIFs can be made natively (e.g. on a server) as well as in the browser.
+//
+// The layout and the colors follow `drawBoard` in `static/js/index.js`: a
+// 540x640 image with a header that shows the names, the scores and a message,
+// and the board below it. The text is drawn with a built-in 5x7 bitmap font
+// (ASCII only).
+
+use crate::{Board, Color};
+
+use std::fmt;
+use std::str::FromStr;
+
+// layout of the image, the same as index.js
+pub const IMAGE_WIDTH:  u32 = 540;
+pub const IMAGE_HEIGHT: u32 = 640;
+const HEADER:       f64 = 100.0;
+const MARGIN:       f64 =  30.0;
+const BOARD_WIDTH:  f64 = 480.0;
+const STONE_STROKE: f64 =   2.0;
+const ROOT_STROKE:  f64 =   5.0;
+const GRID_STROKE:  f64 =   2.0;
+const GUIDE_DASH:   f64 =  10.0;
+const FONT_SCALE:   u32 =   3; // 5x7 font -> 15x21 pixels, close to 20px sans-serif
+const SHEET_GAP:    u32 =  10;
+
+// the largest image made from the board images, in pixels (1 GiB in RGBA)
+pub const MAX_PIXELS: u64 = 1 << 28;
+
+pub type Rgba = [u8; 4];
+
+// colors of the elements. The index of the arrays is the color of a player.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    pub background: Rgba,
+    pub grid:       Rgba,
+    pub text:       Rgba,
+    pub fill:       [Rgba; 2], // inside of stones
+    pub stroke:     [Rgba; 2], // outline of stones, roots and scores
+    pub guide:      [Rgba; 2], // possible moves
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme{
+            background: [255, 255, 255, 255],
+            grid:       [  0,   0,   0, 255],
+            text:       [  0,   0,   0, 255],
+            fill:       [[255, 128, 128, 242], [128, 128, 255, 242]],
+            stroke:     [[255,   0,   0, 255], [  0,   0, 255, 255]],
+            guide:      [[255,   0,   0, 230], [  0,   0, 255, 230]],
+        }
+    }
+}
+
+impl Theme {
+    // light text and lines on a dark background
+    pub fn dark() -> Self {
+        Theme{
+            background: [ 32,  32,  32, 255],
+            grid:       [200, 200, 200, 255],
+            text:       [230, 230, 230, 255],
+            fill:       [[200,  80,  80, 242], [ 80,  96, 200, 242]],
+            stroke:     [[255,  96,  96, 255], [ 96, 128, 255, 255]],
+            guide:      [[255,  96,  96, 230], [ 96, 128, 255, 230]],
+        }
+    }
+
+    fn colors_mut(&mut self) -> [(&'static str, &mut Rgba); 9] {
+        let [red_fill,   blue_fill]   = &mut self.fill;
+        let [red_stroke, blue_stroke] = &mut self.stroke;
+        let [red_guide,  blue_guide]  = &mut self.guide;
+        [("background", &mut self.background), ("grid", &mut self.grid), ("text", &mut self.text),
+         ("red-fill",   red_fill),   ("blue-fill",   blue_fill),
+         ("red-stroke", red_stroke), ("blue-stroke", blue_stroke),
+         ("red-guide",  red_guide),  ("blue-guide",  blue_guide)]
+    }
+}
+
+fn parse_color(text: &str) -> Result<Rgba, String> {
+    let hex = text.strip_prefix('#').unwrap_or(text);
+    let byte = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16)
+        .map_err(|_| format!("invalid color: {}", text));
+    match hex.len() {
+        6 if hex.is_ascii() => Ok([byte(0)?, byte(2)?, byte(4)?, 255]),
+        8 if hex.is_ascii() => Ok([byte(0)?, byte(2)?, byte(4)?, byte(6)?]),
+        _ => Err(format!("invalid color: {}", text)),
+    }
+}
+
+// A theme as text, e.g. "dark" or "light,background=#f0e0c0,red-fill=#ff808080".
+// It starts with "light" (the default) or "dark", and the colors after it
+// overwrite the colors of the preset. A color is #rrggbb or #rrggbbaa.
+// The names of the colors are
+//     background, grid, text, red-fill, blue-fill, red-stroke, blue-stroke,
+//     red-guide, blue-guide
+impl FromStr for Theme {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut items = s.split(',').map(str::trim);
+        let mut theme = match items.next().unwrap() {
+            "light" | "" => Theme::default(),
+            "dark"       => Theme::dark(),
+            preset       => return Err(format!("unknown theme: {}", preset)),
+        };
+        for item in items {
+            let (name, value) = match item.find('=') {
+                Some(i) => (&item[..i], &item[i + 1..]),
+                None    => return Err(format!("expected name=color: {}", item)),
+            };
+            let color = parse_color(value)?;
+            match theme.colors_mut().iter_mut().find(|(n, _)| *n == name) {
+                Some((_, c)) => **c = color,
+                None => return Err(format!("unknown color name: {}", name)),
+            }
+        }
+        Ok(theme)
+    }
+}
+
+// all the colors after "light", which can be parsed again
+impl fmt::Display for Theme {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "light")?;
+        let mut theme = *self;
+        for (name, c) in theme.colors_mut().iter() {
+            write!(f, ",{}=#{:02x}{:02x}{:02x}{:02x}", name, c[0], c[1], c[2], c[3])?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderOptions {
+    pub theme:     Theme,
+    pub red_name:  String,
+    pub blue_name: String,
+    pub message:   String,
+    pub guide:     bool, // draw the possible moves
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions{
+            theme:     Theme::default(),
+            red_name:  "Red".to_string(),
+            blue_name: "Blue".to_string(),
+            message:   String::new(),
+            guide:     false,
+        }
+    }
+}
+
+// an RGBA image, row by row from the top left
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    pub width:  u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+fn coverage(distance: f64) -> f64 {
+    // the fraction of a pixel covered by a shape, approximated by the signed
+    // distance from the pixel center to the edge (positive inside).
+    (distance + 0.5).clamp(0.0, 1.0)
+}
+
+// the distance from p to the segment a-b
+fn distance_to_segment(p: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let len2 = dx * dx + dy * dy;
+    let t = if len2 == 0.0 {
+        0.0
+    } else {
+        (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / len2).clamp(0.0, 1.0)
+    };
+    f64::hypot(p.0 - a.0 - t * dx, p.1 - a.1 - t * dy)
+}
+
+impl Image {
+    pub fn new(width: u32, height: u32, color: Rgba) -> Self {
+        let pixels = color.iter().cloned().cycle().take((width * height * 4) as usize).collect();
+        Image{width, height, pixels}
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> Rgba {
+        let i = ((y * self.width + x) * 4) as usize;
+        [self.pixels[i], self.pixels[i + 1], self.pixels[i + 2], self.pixels[i + 3]]
+    }
+
+    // "source over" blending, with the alpha of the color times `coverage`
+    fn blend(&mut self, x: i64, y: i64, color: Rgba, coverage: f64) {
+        if x < 0 || y < 0 || self.width as i64 <= x || self.height as i64 <= y || coverage <= 0.0 {
+            return;
+        }
+        let i = ((y as u32 * self.width + x as u32) * 4) as usize;
+        let src_a = color[3] as f64 / 255.0 * coverage;
+        let dst_a = self.pixels[i + 3] as f64 / 255.0;
+        let out_a = src_a + dst_a * (1.0 - src_a);
+        if out_a <= 0.0 {
+            return;
+        }
+        for (c, src) in color.iter().take(3).enumerate() {
+            let src = *src as f64;
+            let dst = self.pixels[i + c] as f64;
+            self.pixels[i + c] = ((src * src_a + dst * dst_a * (1.0 - src_a)) / out_a).round() as u8;
+        }
+        self.pixels[i + 3] = (out_a * 255.0).round() as u8;
+    }
+
+    // calls `f` with each pixel in the bounding box and the pixel center
+    fn shade<F: Fn(f64, f64) -> f64>(&mut self, bbox: (f64, f64, f64, f64), color: Rgba, f: F) {
+        let (x0, y0, x1, y1) = bbox;
+        for y in (y0.floor() as i64 - 1)..=(y1.ceil() as i64) {
+            for x in (x0.floor() as i64 - 1)..=(x1.ceil() as i64) {
+                let c = f(x as f64 + 0.5, y as f64 + 0.5);
+                self.blend(x, y, color, c);
+            }
+        }
+    }
+
+    pub fn fill_rect(&mut self, x: f64, y: f64, w: f64, h: f64, color: Rgba) {
+        self.shade((x, y, x + w, y + h), color, |px, py| {
+            let cx = (px + 0.5).min(x + w) - (px - 0.5).max(x);
+            let cy = (py + 0.5).min(y + h) - (py - 0.5).max(y);
+            cx.max(0.0) * cy.max(0.0)
+        });
+    }
+
+    pub fn fill_circle(&mut self, cx: f64, cy: f64, r: f64, color: Rgba) {
+        self.shade((cx - r, cy - r, cx + r, cy + r), color, |px, py| {
+            coverage(r - f64::hypot(px - cx, py - cy))
+        });
+    }
+
+    // a ring of width `w` centered on the circle of radius `r`
+    pub fn stroke_circle(&mut self, cx: f64, cy: f64, r: f64, w: f64, color: Rgba) {
+        let outer = r + w / 2.0;
+        self.shade((cx - outer, cy - outer, cx + outer, cy + outer), color, |px, py| {
+            coverage(w / 2.0 - (f64::hypot(px - cx, py - cy) - r).abs())
+        });
+    }
+
+    // a line of width `w` with round caps
+    pub fn line(&mut self, a: (f64, f64), b: (f64, f64), w: f64, color: Rgba) {
+        let h = w / 2.0;
+        let bbox = (a.0.min(b.0) - h, a.1.min(b.1) - h, a.0.max(b.0) + h, a.1.max(b.1) + h);
+        self.shade(bbox, color, |px, py| coverage(h - distance_to_segment((px, py), a, b)));
+    }
+
+    // a dashed line, `dash` on and `dash` off, each dash with round caps
+    pub fn dashed_line(&mut self, a: (f64, f64), b: (f64, f64), w: f64, dash: f64, color: Rgba) {
+        let len = f64::hypot(b.0 - a.0, b.1 - a.1);
+        let mut s = 0.0;
+        while s < len {
+            let e = (s + dash).min(len);
+            let p = |t: f64| (a.0 + (b.0 - a.0) * t / len, a.1 + (b.1 - a.1) * t / len);
+            self.line(p(s), p(e), w, color);
+            s += 2.0 * dash;
+        }
+    }
+
+    // resizes the image by `scale`. Each pixel of the result is the average
+    // of the pixels it covers, weighted by the overlapping area.
+    pub fn scaled(&self, scale: f64) -> Image {
+        let width  = ((self.width  as f64 * scale).round() as u32).max(1);
+        let height = ((self.height as f64 * scale).round() as u32).max(1);
+        if (width, height) == (self.width, self.height) {
+            return self.clone();
+        }
+        let (sx, sy) = (self.width as f64 / width as f64, self.height as f64 / height as f64);
+        let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+        for y in 0..height {
+            let (y0, y1) = (y as f64 * sy, (y + 1) as f64 * sy);
+            for x in 0..width {
+                let (x0, x1) = (x as f64 * sx, (x + 1) as f64 * sx);
+                let mut sum = [0.0; 4];
+                let mut total = 0.0;
+                for py in (y0.floor() as u32)..(y1.ceil() as u32).min(self.height) {
+                    let wy = (y1.min(py as f64 + 1.0) - y0.max(py as f64)).max(0.0);
+                    for px in (x0.floor() as u32)..(x1.ceil() as u32).min(self.width) {
+                        let wx = (x1.min(px as f64 + 1.0) - x0.max(px as f64)).max(0.0);
+                        let pixel = self.pixel(px, py);
+                        for (s, p) in sum.iter_mut().zip(pixel.iter()) {
+                            *s += *p as f64 * wx * wy;
+                        }
+                        total += wx * wy;
+                    }
+                }
+                pixels.extend(sum.iter().map(|s| (s / total).round() as u8));
+            }
+        }
+        Image{width, height, pixels}
+    }
+
+    pub fn text_width(text: &str) -> u32 {
+        text.chars().count() as u32 * 6 * FONT_SCALE
+    }
+
+    // draws text with its top left corner at (x, y)
+    pub fn text(&mut self, x: i64, y: i64, text: &str, color: Rgba) {
+        let scale = FONT_SCALE as i64;
+        for (i, ch) in text.chars().enumerate() {
+            let glyph = glyph(ch);
+            let left = x + i as i64 * 6 * scale;
+            for (col, bits) in glyph.iter().enumerate() {
+                for row in 0..7 {
+                    if bits >> row & 1 == 0 {
+                        continue;
+                    }
+                    for dy in 0..scale {
+                        for dx in 0..scale {
+                            self.blend(left + col as i64 * scale + dx, y + row * scale + dy, color, 1.0);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+// the size of a contact sheet of `count` images of `width`x`height` in
+// `columns`. It fails if the sheet would have more than `MAX_PIXELS` pixels.
+pub fn contact_sheet_size(count: usize, columns: usize, width: u32, height: u32)
+    -> Result<(u32, u32), String> {
+    let columns = columns.max(1) as u64;
+    let rows = (count as u64).div_ceil(columns).max(1);
+    let sheet_width  = columns * (width  as u64 + SHEET_GAP as u64) + SHEET_GAP as u64;
+    let sheet_height = rows    * (height as u64 + SHEET_GAP as u64) + SHEET_GAP as u64;
+    if sheet_width.saturating_mul(sheet_height) > MAX_PIXELS {
+        return Err(format!("the contact sheet of {} frames is too large: {}x{}",
+                           count, sheet_width, sheet_height));
+    }
+    Ok((sheet_width as u32, sheet_height as u32))
+}
+
+// images in a grid of `columns`, from the left top, with a gap between them.
+// All the images should have the size of the first one. The images are taken
+// one at a time, so they can be decoded as they are drawn.
+pub fn contact_sheet<I>(images: I, columns: usize, background: Rgba) -> Result<Image, String>
+    where I: ExactSizeIterator<Item = Image> {
+    let count = images.len();
+    let mut images = images.peekable();
+    let (width, height) = images.peek().map_or((1, 1), |image| (image.width, image.height));
+    let (sheet_width, sheet_height) = contact_sheet_size(count, columns, width, height)?;
+    let columns = columns.max(1);
+    let mut sheet = Image::new(sheet_width, sheet_height, background);
+    for (i, image) in images.enumerate() {
+        let left = (i % columns) as u32 * (width + SHEET_GAP) + SHEET_GAP;
+        let top  = (i / columns) as u32 * (height + SHEET_GAP) + SHEET_GAP;
+        for y in 0..image.height.min(height) {
+            let src = (y * image.width * 4) as usize;
+            let dst = (((top + y) * sheet.width + left) * 4) as usize;
+            let len = (image.width.min(width) * 4) as usize;
+            sheet.pixels[dst..dst + len].copy_from_slice(&image.pixels[src..src + len]);
+        }
+    }
+    Ok(sheet)
+}
+
+fn to_pixel(x: i8, y: i8, grid_width: f64) -> (f64, f64) {
+    (x as f64 * grid_width + MARGIN, y as f64 * grid_width + MARGIN + HEADER)
+}
+
+pub fn render(board: &Board, options: &RenderOptions) -> Image {
+    let theme  = &options.theme;
+    let width  = board.width as usize;
+    let grid_width   = BOARD_WIDTH / (width - 1) as f64;
+    let stone_radius = grid_width * 0.3;
+    let mut image = Image::new(IMAGE_WIDTH, IMAGE_HEIGHT, theme.background);
+
+    // header: "red: 1 | blue: 2" around the center, and the message below
+    let mut scores = board.clone();
+    let red_text  = format!("{}: {}", options.red_name,  scores.score(Color::Red));
+    let blue_text = format!("{}: {}", options.blue_name, scores.score(Color::Blue));
+    let bar   = Image::text_width(" | ") as i64;
+    let top   = 40 - 7 * FONT_SCALE as i64; // 40 is the baseline in index.js
+    let center = IMAGE_WIDTH as i64 / 2;
+    image.text(center - bar / 2 - Image::text_width(&red_text) as i64, top, &red_text,
+               theme.stroke[Color::Red as usize]);
+    image.text(center + bar / 2, top, &blue_text, theme.stroke[Color::Blue as usize]);
+    image.text(center - Image::text_width("|") as i64 / 2, top, "|", theme.text);
+    image.text(center - Image::text_width(&options.message) as i64 / 2, top + 30,
+               &options.message, theme.text);
+
+    // grid
+    let last = (width - 1) as i8;
+    for i in 0..width as i8 {
+        let (x, y0) = to_pixel(i, 0, grid_width);
+        let (_, y1) = to_pixel(i, last, grid_width);
+        image.fill_rect(x - GRID_STROKE / 2.0, y0, GRID_STROKE, y1 - y0, theme.grid);
+        let (x0, y) = to_pixel(0, i, grid_width);
+        let (x1, _) = to_pixel(last, i, grid_width);
+        image.fill_rect(x0, y - GRID_STROKE / 2.0, x1 - x0, GRID_STROKE, theme.grid);
+    }
+
+    // stones, then roots on them
+    for (idx, grid) in board.grids.iter().enumerate() {
+        if let Some(color) = grid.color {
+            let (x, y) = to_pixel((idx / width) as i8, (idx % width) as i8, grid_width);
+            image.fill_circle(x, y, stone_radius, theme.fill[color as usize]);
+            image.stroke_circle(x, y, stone_radius, STONE_STROKE, theme.stroke[color as usize]);
+        }
+    }
+    for (idx, grid) in board.grids.iter().enumerate() {
+        if let Some(color) = grid.color {
+            let (x, y) = ((idx / width) as i8, (idx % width) as i8);
+            for dir in grid.roots.iter() {
+                image.line(to_pixel(x, y, grid_width), to_pixel(x + dir.0, y + dir.1, grid_width),
+                           ROOT_STROKE, theme.stroke[color as usize]);
+            }
+        }
+    }
+
+    if options.guide {
+        for color in [Color::Red, Color::Blue].iter() {
+            for mv in board.possible_moves(*color) {
+                let crate::Move(stone1, stone2, stone3) = mv;
+                let p1 = to_pixel(stone1.x, stone1.y, grid_width);
+                let p2 = to_pixel(stone2.x, stone2.y, grid_width);
+                let p3 = to_pixel(stone3.x, stone3.y, grid_width);
+                image.dashed_line(p1, p2, ROOT_STROKE, GUIDE_DASH, theme.guide[*color as usize]);
+                image.dashed_line(p2, p3, ROOT_STROKE, GUIDE_DASH, theme.guide[*color as usize]);
+            }
+        }
+    }
+    image
+}
+
+// 5x7 font for ASCII 0x20..0x7E. Each glyph is 5 columns from the left and
+// the bit n of a column is the n-th row from the top.
+const FONT: [[u8; 5]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00], [0x00, 0x00, 0x5F, 0x00, 0x00], // ' ' !
+    [0x00, 0x07, 0x00, 0x07, 0x00], [0x14, 0x7F, 0x14, 0x7F, 0x14], // " #
+    [0x24, 0x2A, 0x7F, 0x2A, 0x12], [0x23, 0x13, 0x08, 0x64, 0x62], // $ %
+    [0x36, 0x49, 0x56, 0x20, 0x50], [0x00, 0x05, 0x03, 0x00, 0x00], // & '
+    [0x00, 0x1C, 0x22, 0x41, 0x00], [0x00, 0x41, 0x22, 0x1C, 0x00], // ( )
+    [0x14, 0x08, 0x3E, 0x08, 0x14], [0x08, 0x08, 0x3E, 0x08, 0x08], // * +
+    [0x00, 0x50, 0x30, 0x00, 0x00], [0x08, 0x08, 0x08, 0x08, 0x08], // , -
+    [0x00, 0x60, 0x60, 0x00, 0x00], [0x20, 0x10, 0x08, 0x04, 0x02], // . /
+    [0x3E, 0x51, 0x49, 0x45, 0x3E], [0x00, 0x42, 0x7F, 0x40, 0x00], // 0 1
+    [0x42, 0x61, 0x51, 0x49, 0x46], [0x21, 0x41, 0x45, 0x4B, 0x31], // 2 3
+    [0x18, 0x14, 0x12, 0x7F, 0x10], [0x27, 0x45, 0x45, 0x45, 0x39], // 4 5
+    [0x3C, 0x4A, 0x49, 0x49, 0x30], [0x01, 0x71, 0x09, 0x05, 0x03], // 6 7
+    [0x36, 0x49, 0x49, 0x49, 0x36], [0x06, 0x49, 0x49, 0x29, 0x1E], // 8 9
+    [0x00, 0x36, 0x36, 0x00, 0x00], [0x00, 0x56, 0x36, 0x00, 0x00], // : ;
+    [0x08, 0x14, 0x22, 0x41, 0x00], [0x14, 0x14, 0x14, 0x14, 0x14], // < =
+    [0x00, 0x41, 0x22, 0x14, 0x08], [0x02, 0x01, 0x51, 0x09, 0x06], // > ?
+    [0x32, 0x49, 0x79, 0x41, 0x3E], [0x7E, 0x11, 0x11, 0x11, 0x7E], // @ A
+    [0x7F, 0x49, 0x49, 0x49, 0x36], [0x3E, 0x41, 0x41, 0x41, 0x22], // B C
+    [0x7F, 0x41, 0x41, 0x22, 0x1C], [0x7F, 0x49, 0x49, 0x49, 0x41], // D E
+    [0x7F, 0x09, 0x09, 0x09, 0x01], [0x3E, 0x41, 0x49, 0x49, 0x7A], // F G
+    [0x7F, 0x08, 0x08, 0x08, 0x7F], [0x00, 0x41, 0x7F, 0x41, 0x00], // H I
+    [0x20, 0x40, 0x41, 0x3F, 0x01], [0x7F, 0x08, 0x14, 0x22, 0x41], // J K
+    [0x7F, 0x40, 0x40, 0x40, 0x40], [0x7F, 0x02, 0x0C, 0x02, 0x7F], // L M
+    [0x7F, 0x04, 0x08, 0x10, 0x7F], [0x3E, 0x41, 0x41, 0x41, 0x3E], // N O
+    [0x7F, 0x09, 0x09, 0x09, 0x06], [0x3E, 0x41, 0x51, 0x21, 0x5E], // P Q
+    [0x7F, 0x09, 0x19, 0x29, 0x46], [0x46, 0x49, 0x49, 0x49, 0x31], // R S
+    [0x01, 0x01, 0x7F, 0x01, 0x01], [0x3F, 0x40, 0x40, 0x40, 0x3F], // T U
+    [0x1F, 0x20, 0x40, 0x20, 0x1F], [0x3F, 0x40, 0x38, 0x40, 0x3F], // V W
+    [0x63, 0x14, 0x08, 0x14, 0x63], [0x07, 0x08, 0x70, 0x08, 0x07], // X Y
+    [0x61, 0x51, 0x49, 0x45, 0x43], [0x00, 0x7F, 0x41, 0x41, 0x00], // Z [
+    [0x02, 0x04, 0x08, 0x10, 0x20], [0x00, 0x41, 0x41, 0x7F, 0x00], // \ ]
+    [0x04, 0x02, 0x01, 0x02, 0x04], [0x40, 0x40, 0x40, 0x40, 0x40], // ^ _
+    [0x00, 0x01, 0x02, 0x04, 0x00], [0x20, 0x54, 0x54, 0x54, 0x78], // ` a
+    [0x7F, 0x48, 0x44, 0x44, 0x38], [0x38, 0x44, 0x44, 0x44, 0x20], // b c
+    [0x38, 0x44, 0x44, 0x48, 0x7F], [0x38, 0x54, 0x54, 0x54, 0x18], // d e
+    [0x08, 0x7E, 0x09, 0x01, 0x02], [0x0C, 0x52, 0x52, 0x52, 0x3E], // f g
+    [0x7F, 0x08, 0x04, 0x04, 0x78], [0x00, 0x44, 0x7D, 0x40, 0x00], // h i
+    [0x20, 0x40, 0x44, 0x3D, 0x00], [0x7F, 0x10, 0x28, 0x44, 0x00], // j k
+    [0x00, 0x41, 0x7F, 0x40, 0x00], [0x7C, 0x04, 0x18, 0x04, 0x78], // l m
+    [0x7C, 0x08, 0x04, 0x04, 0x78], [0x38, 0x44, 0x44, 0x44, 0x38], // n o
+    [0x7C, 0x14, 0x14, 0x14, 0x08], [0x08, 0x14, 0x14, 0x18, 0x7C], // p q
+    [0x7C, 0x08, 0x04, 0x04, 0x08], [0x48, 0x54, 0x54, 0x54, 0x20], // r s
+    [0x04, 0x3F, 0x44, 0x40, 0x20], [0x3C, 0x40, 0x40, 0x20, 0x7C], // t u
+    [0x1C, 0x20, 0x40, 0x20, 0x1C], [0x3C, 0x40, 0x30, 0x40, 0x3C], // v w
+    [0x44, 0x28, 0x10, 0x28, 0x44], [0x0C, 0x50, 0x50, 0x50, 0x3C], // x y
+    [0x44, 0x64, 0x54, 0x4C, 0x44], [0x00, 0x08, 0x36, 0x41, 0x00], // z {
+    [0x00, 0x00, 0x7F, 0x00, 0x00], [0x00, 0x41, 0x36, 0x08, 0x00], // | }
+    [0x08, 0x04, 0x08, 0x10, 0x08],                                 // ~
+];
+
+// characters out of the range are drawn as '?'
+fn glyph(ch: char) -> &'static [u8; 5] {
+    match ch {
+        ' '..='~' => &FONT[ch as usize - 0x20],
+        _         => &FONT['?' as usize - 0x20],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Move;
+
+    #[test]
+    fn theme_from_text() {
+        assert_eq!("light".parse::<Theme>().unwrap(), Theme::default());
+        assert_eq!("dark".parse::<Theme>().unwrap(), Theme::dark());
+        let theme: Theme = "dark, background=#102030, red-fill=#ff808080".parse().unwrap();
+        assert_eq!(theme.background, [0x10, 0x20, 0x30, 255]);
+        assert_eq!(theme.fill[0], [255, 128, 128, 128]);
+        assert_eq!(theme.grid, Theme::dark().grid);
+        assert_eq!(theme.to_string().parse::<Theme>().unwrap(), theme);
+
+        assert!("sepia".parse::<Theme>().is_err());
+        assert!("light,background".parse::<Theme>().is_err());
+        assert!("light,board=#ffffff".parse::<Theme>().is_err());
+        assert!("light,grid=#fffff".parse::<Theme>().is_err());
+        assert!("light,grid=#ééé".parse::<Theme>().is_err());
+    }
+
+    #[test]
+    fn render_board() {
+        let mut board = Board::new(9);
+        let red: Move = "a1-b2-b3".parse().unwrap();
+        board.apply_move(red, Color::Red);
+
+        let options = RenderOptions{message: "Blue's turn".to_string(), ..RenderOptions::default()};
+        let image = render(&board, &options);
+        assert_eq!((image.width, image.height), (IMAGE_WIDTH, IMAGE_HEIGHT));
+        assert_eq!(image.pixels.len(), (IMAGE_WIDTH * IMAGE_HEIGHT * 4) as usize);
+
+        let theme = Theme::default();
+        // grid width is 60 and the board starts at (30, 130)
+        assert_eq!(image.pixel(5, 600), theme.background);
+        assert_eq!(image.pixel(30, 160), theme.grid);               // on a line
+        assert_eq!(image.pixel(60, 220), theme.background);         // between lines
+        assert_eq!(image.pixel(90, 220), theme.stroke[0]);          // the root b2-b3
+        assert_eq!(image.pixel(90 + 8, 190 - 8), [255, 134, 134, 255]);  // red stone over white
+
+        // a stone of blue at the corner
+        let corner = image.pixel(30 + 8 * 60 + 10, 130 - 8);
+        assert_eq!(corner, [134, 134, 255, 255]);
+
+        // the header has some text
+        assert!((0..100).any(|y| (0..IMAGE_WIDTH).any(|x| image.pixel(x, y) == theme.text)));
+
+        let guided = render(&board, &RenderOptions{guide: true, ..options});
+        assert_ne!(guided, image);
+
+        let half = image.scaled(0.5);
+        assert_eq!((half.width, half.height), (IMAGE_WIDTH / 2, IMAGE_HEIGHT / 2));
+        assert_eq!(half.pixel(2, 300), theme.background);
+        assert_eq!(image.scaled(1.0), image);
+    }
+}
@@ -0,0 +1,187 @@
+// Self-play data generation for learning-based engines.
+//
+// UCTMonteCarlo plays against itself and every position where a player has a
+// choice is written as a line of JSON.
+//
+//     {"size": 9, "ply": 0, "turn": "red", "board": <Board::to_json>,
+//      "planes": [0.0, 1.0, ...], "moves": ["a1-b2-b3", ...],
+//      "policy": [0, 57, ...], "visits": [12, 3, ...], "result": 1}
+//
+//   - planes: the input features, `Board::to_planes` seen from the side to
+//             move (NUM_PLANES x size x size).
+//   - moves:  the possible moves of the side to move.
+//   - policy: `Move::policy_index` of each move.
+//   - visits: the number of playouts through each move at the root, i.e.
+//             the target policy.
+//   - result: the final result for the side to move. 1 (win), 0 (draw) or
+//             -1 (lose).
+//
+// The first moves are sampled with temperature (see `set_temperature`) so
+// that the games are diverse. Resignation is disabled.
+
+use crate::{Board, Color, Move, UCTMonteCarlo};
+use crate::game_record::GameRecord;
+use crate::tournament::game_seed;
+
+use serde_json::{json, Value};
+
+use std::io::{self, Write};
+
+pub struct SelfPlay {
+    pub size:              usize,
+    pub playouts:          u32, // per move
+    pub time_limit:        u32, // per move, in seconds. in case playouts take too long
+    pub ucb1_coeff:        f64,
+    pub expand_threshold:  u32,
+    pub temperature:       f64,
+    pub temperature_moves: u32,
+    pub seed:              u64,
+}
+
+impl Default for SelfPlay {
+    fn default() -> Self {
+        SelfPlay{
+            size:              9,
+            playouts:          1000,
+            time_limit:        60,
+            ucb1_coeff:        2.0,
+            expand_threshold:  3,
+            temperature:       1.0,
+            temperature_moves: 8,
+            seed:              0,
+        }
+    }
+}
+
+pub struct Position {
+    pub ply:    usize,
+    pub turn:   Color,
+    pub board:  Board,
+    pub visits: Vec<(Move, u32)>,
+    pub result: i8, // for the side to move
+}
+
+impl Position {
+    pub fn to_json(&self) -> Value {
+        let board: Value = serde_json::from_str(&self.board.to_json()).unwrap();
+        let width = self.board.width as usize;
+        json!({
+            "size":   self.board.width,
+            "ply":    self.ply,
+            "turn":   self.turn.to_string(),
+            "board":  board,
+            "planes": self.board.to_planes(self.turn),
+            "moves":  self.visits.iter().map(|(m, _)| m.to_string()).collect::<Vec<_>>(),
+            "policy": self.visits.iter()
+                .map(|(m, _)| m.policy_index(width).unwrap()).collect::<Vec<_>>(),
+            "visits": self.visits.iter().map(|(_, n)| *n).collect::<Vec<_>>(),
+            "result": self.result,
+        })
+    }
+}
+
+impl SelfPlay {
+    fn player(&self, color: Color, seed: u64) -> UCTMonteCarlo {
+        let mut player = UCTMonteCarlo::new(color, seed as u32, (seed >> 32) as u32,
+            self.time_limit, self.ucb1_coeff, self.expand_threshold, self.size);
+        player.max_playouts = self.playouts;
+        player.set_temperature(self.temperature, self.temperature_moves);
+        player
+    }
+
+    // play the `game`-th game and return the positions and the record.
+    pub fn play_game(&self, game: u64) -> (Vec<Position>, GameRecord) {
+        let seed = game_seed(self.seed, game);
+        let mut players = [self.player(Color::Red,  game_seed(seed, 0)),
+                           self.player(Color::Blue, game_seed(seed, 1))];
+        let mut record = GameRecord::new(self.size, "self-play".to_string(), "self-play".to_string());
+        record.tags.push(("Seed".to_string(), seed.to_string()));
+
+        let mut positions = Vec::new();
+        let mut board = Board::new(self.size);
+        while !board.is_gameover() && !board.is_decided() {
+            let turn = GameRecord::turn_of(record.moves.len());
+            let player = &mut players[turn as usize];
+            let next_move = player.genmove(&board);
+            if let Some(next_move) = next_move {
+                positions.push(Position{
+                    ply:    record.moves.len(),
+                    turn,
+                    board:  board.clone(),
+                    visits: player.last_visits.clone(),
+                    result: 0,
+                });
+                board.apply_move(next_move, turn);
+            }
+            record.moves.push(next_move);
+        }
+
+        let winner = record.winner().unwrap();
+        for position in positions.iter_mut() {
+            position.result = match winner {
+                Some(color) if color == position.turn => 1,
+                Some(_) => -1,
+                None    => 0,
+            };
+        }
+        (positions, record)
+    }
+
+    // write the positions of `games` games as JSON lines. It returns the
+    // number of positions written. `on_game` is called with the record of
+    // each game, e.g. to keep an archive for an opening book.
+    pub fn generate<W, F>(&self, games: u64, mut out: W, mut on_game: F) -> io::Result<usize>
+        where W: Write, F: FnMut(&GameRecord) {
+        let mut count = 0;
+        for game in 0..games {
+            let (positions, record) = self.play_game(game);
+            for position in positions.iter() {
+                writeln!(out, "{}", position.to_json())?;
+            }
+            count += positions.len();
+            on_game(&record);
+        }
+        out.flush()?;
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selfplay_positions() {
+        let selfplay = SelfPlay{size: 5, playouts: 50, ..SelfPlay::default()};
+        let (positions, record) = selfplay.play_game(0);
+        assert!(!positions.is_empty());
+
+        let winner = record.winner().unwrap();
+        let boards = record.replay().unwrap();
+        for position in positions.iter() {
+            assert_eq!(position.board, boards[position.ply]);
+            let mut moves = position.board.possible_moves(position.turn);
+            let mut visited = position.visits.iter().map(|(m, _)| *m).collect::<Vec<_>>();
+            moves.sort_by_key(|m| m.to_string());
+            visited.sort_by_key(|m| m.to_string());
+            assert_eq!(moves, visited);
+
+            let total = position.visits.iter().map(|(_, n)| *n).sum::<u32>();
+            // 50 new playouts, and those reused from the last search
+            assert!(50 <= total);
+            assert_eq!(position.result == 1, winner == Some(position.turn));
+        }
+
+        let mut out = Vec::new();
+        let mut records = Vec::new();
+        let count = selfplay.generate(1, &mut out, |r| records.push(r.clone())).unwrap();
+        assert_eq!(count, positions.len());
+        assert_eq!(records, vec![record]);
+        let lines = String::from_utf8(out).unwrap();
+        let first: Value = serde_json::from_str(lines.lines().next().unwrap()).unwrap();
+        assert_eq!(first, positions[0].to_json());
+        assert_eq!(first["turn"], "red");
+        assert_eq!(first["planes"].as_array().unwrap().len(), crate::NUM_PLANES * 5 * 5);
+        assert_eq!(first["policy"][0], positions[0].visits[0].0.policy_index(5).unwrap());
+    }
+}
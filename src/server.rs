@@ -0,0 +1,647 @@
+// A game server for networked play. Each room holds the authoritative board
+// and the clients talk to it over WebSocket with JSON messages.
+//
+// client -> server
+//
+//     {"type": "join",   "room": "foo", "seat": "red", "size": 9}
+//     {"type": "move",   "move": "a1-b2-b3"}
+//     {"type": "engine", "color": "blue", "engine": "uct:time=1"}
+//     {"type": "resign"}
+//     {"type": "reset",  "size": 9}
+//
+// `seat` is "red", "blue", or "spectator" (watch only, same as omitted). `size`
+// is used only when the room is created. Any client in a room can seat an
+// engine on an open seat, so a room can also be engine vs engine.
+//
+// server -> client
+//
+//     {"type": "state", "room": "foo", "size": 9, "board": <Board::to_json>,
+//      "turn": "red", "last_move": null, "scores": {"red": 1, "blue": 1},
+//      "seats": {"red": "human", "blue": "uct:time=1,coeff=2,expand=3"},
+//      "gameover": false, "result": null}
+//     {"type": "error", "message": "illegal move"}
+//
+// If an engine fails, its seat is opened and the room is told so with an error
+// message.
+//
+//     {"type": "ply", "ply": 1, "color": "red", "move": "a1-b2-b3",
+//      "scores": {"red": 1, "blue": 1}, "search": null}
+//     {"type": "history", "plies": [<ply>, ...]}
+//
+// `turn` is null after the game ends. `result` is "red", "blue" or "draw".
+// The state is sent to everyone in the room whenever it changes. Each ply is
+// also sent as a `ply` message with the scores after the move and, if an
+// engine played it, the search statistics (see `SearchInfo`). `move` is
+// "resign" if the player resigned. A client that joins a room receives the
+// plies played so far as a `history` message first, so that spectators can
+// catch up with the game.
+
+use crate::{Board, Color, Move, opponent_of};
+use crate::engine::{EngineConfig, SearchInfo};
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tungstenite::{Message, WebSocket};
+
+use std::collections::HashMap;
+use std::io;
+use std::net::{TcpListener, TcpStream, SocketAddr, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{channel, Sender};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const DEFAULT_BOARD_SIZE: usize = 9;
+const MIN_BOARD_SIZE:     usize = 4;
+const MAX_BOARD_SIZE:     usize = 19;
+
+// how long a connection waits for a message before it checks the updates.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Request {
+    Join{room: String, seat: Option<String>, size: Option<usize>},
+    Move{#[serde(rename = "move")] next_move: String},
+    Engine{color: String, engine: String},
+    Resign,
+    Reset{size: Option<usize>},
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Seat {
+    Open,
+    Human(usize), // client id
+    Engine(EngineConfig),
+}
+
+struct Client {
+    id:     usize,
+    sender: Sender<String>,
+}
+
+struct Room {
+    name:      String,
+    board:     Board,
+    turn:      Option<Color>, // None if the game has ended
+    seats:     [Seat; 2],
+    clients:   Vec<Client>,
+    last_move: Option<Move>,
+    resigned:  Option<Color>,
+    history:   Vec<Value>,    // ply messages
+    thinking:  bool,          // an engine is searching the next move
+    seed:      u64,
+}
+
+fn parse_size(size: Option<usize>) -> Result<usize, String> {
+    let size = size.unwrap_or(DEFAULT_BOARD_SIZE);
+    if !(MIN_BOARD_SIZE..=MAX_BOARD_SIZE).contains(&size) {
+        return Err(format!("board size must be in [{}, {}]", MIN_BOARD_SIZE, MAX_BOARD_SIZE));
+    }
+    Ok(size)
+}
+
+fn error_message(message: &str) -> String {
+    json!({"type": "error", "message": message}).to_string()
+}
+
+impl Room {
+    fn new(name: String, width: usize, seed: u64) -> Self {
+        Room{
+            name,
+            board:     Board::new(width),
+            turn:      Some(Color::Red),
+            seats:     [Seat::Open, Seat::Open],
+            clients:   Vec::new(),
+            last_move: None,
+            resigned:  None,
+            history:   Vec::new(),
+            thinking:  false,
+            seed,
+        }
+    }
+
+    fn reset(&mut self, width: usize) {
+        self.board     = Board::new(width);
+        self.turn      = Some(Color::Red);
+        self.last_move = None;
+        self.resigned  = None;
+        self.history.clear();
+    }
+
+    fn seat_of(&self, client: usize) -> Option<Color> {
+        [Color::Red, Color::Blue].iter().copied()
+            .find(|color| self.seats[*color as usize] == Seat::Human(client))
+    }
+
+    fn play(&mut self, color: Color, next_move: Move, search: Option<SearchInfo>)
+        -> Result<(), String> {
+        if self.turn != Some(color) {
+            return Err("not your turn".to_string());
+        }
+        if !self.board.is_valid_move(next_move, color) {
+            return Err("illegal move".to_string());
+        }
+        self.board.apply_move(next_move, color);
+        self.last_move = Some(next_move);
+        self.advance_turn(color);
+        self.record(color, next_move.to_string(), search);
+        Ok(())
+    }
+
+    fn resign(&mut self, color: Color, search: Option<SearchInfo>) -> Result<(), String> {
+        if self.turn.is_none() {
+            return Err("the game has already ended".to_string());
+        }
+        self.resigned = Some(color);
+        self.turn     = None;
+        self.record(color, "resign".to_string(), search);
+        Ok(())
+    }
+
+    fn record(&mut self, color: Color, next_move: String, search: Option<SearchInfo>) {
+        let ply = json!({
+            "type":   "ply",
+            "ply":    self.history.len() + 1,
+            "color":  color.to_string(),
+            "move":   next_move,
+            "scores": {"red":  self.board.score(Color::Red),
+                       "blue": self.board.score(Color::Blue)},
+            "search": search,
+        });
+        self.broadcast(&ply.to_string());
+        self.history.push(ply);
+    }
+
+    // A player who has no possible move passes. The game ends when neither
+    // player can move, or when no move can change the scores.
+    fn advance_turn(&mut self, color: Color) {
+        let next = opponent_of(color);
+        self.turn = if self.board.is_gameover() || self.board.is_decided() {
+            None
+        } else if self.board.can_move(next) {
+            Some(next)
+        } else {
+            Some(color)
+        };
+    }
+
+    fn result(&mut self) -> Option<&'static str> {
+        if self.turn.is_some() {
+            return None;
+        }
+        if let Some(color) = self.resigned {
+            return Some(if color == Color::Red {"blue"} else {"red"});
+        }
+        let red  = self.board.score(Color::Red);
+        let blue = self.board.score(Color::Blue);
+        Some(if red > blue {"red"} else if blue > red {"blue"} else {"draw"})
+    }
+
+    fn state(&mut self) -> String {
+        let board: Value = serde_json::from_str(&self.board.to_json()).unwrap();
+        let seats = self.seats.iter().map(|seat| match seat {
+            Seat::Open         => "open".to_string(),
+            Seat::Human(_)     => "human".to_string(),
+            Seat::Engine(spec) => spec.to_string(),
+        }).collect::<Vec<_>>();
+        json!({
+            "type":      "state",
+            "room":      self.name,
+            "size":      self.board.width,
+            "board":     board,
+            "turn":      self.turn.map(|c| c.to_string()),
+            "last_move": self.last_move.map(|m| m.to_string()),
+            "scores":    {"red":  self.board.score(Color::Red),
+                          "blue": self.board.score(Color::Blue)},
+            "seats":     {"red": seats[0], "blue": seats[1]},
+            "gameover":  self.turn.is_none(),
+            "result":    self.result(),
+        }).to_string()
+    }
+
+    // send a message to all the clients. Disconnected clients are removed.
+    fn broadcast(&mut self, message: &str) {
+        self.clients.retain(|client| client.sender.send(message.to_string()).is_ok());
+    }
+
+    fn broadcast_state(&mut self) {
+        let state = self.state();
+        self.broadcast(&state);
+    }
+
+    // an engine failed, e.g. it returned an illegal move. The seat is opened
+    // so that the game can go on with another player.
+    fn stop_engine(&mut self, color: Color, reason: &str) {
+        self.seats[color as usize] = Seat::Open;
+        self.broadcast(&error_message(&format!("{} engine stopped: {}", color, reason)));
+        self.broadcast_state();
+    }
+}
+
+// If it is an engine's turn, search the next move in another thread so that
+// the room is not locked while searching. The engines are built for each move
+// because they are not `Send`.
+fn start_engine(room_ptr: Arc<Mutex<Room>>) {
+    let (board, turn, config, seed) = {
+        let mut room = room_ptr.lock().unwrap();
+        let turn = match room.turn {
+            Some(turn) => turn,
+            None       => return,
+        };
+        let config = match room.seats[turn as usize] {
+            Seat::Engine(ref config) => config.clone(),
+            _ => return,
+        };
+        if room.thinking {
+            return;
+        }
+        room.thinking = true;
+        room.seed     = room.seed.wrapping_add(1);
+        (room.board.clone(), turn, config, room.seed)
+    };
+
+    thread::spawn(move || {
+        // e.g. the room was reset to a size that the engine does not support
+        let mut engine = match config.build(turn, seed, board.width as usize) {
+            Ok(engine) => engine,
+            Err(err) => {
+                let mut room = room_ptr.lock().unwrap();
+                room.thinking = false;
+                if room.seats[turn as usize] == Seat::Engine(config) {
+                    room.stop_engine(turn, &err);
+                }
+                return;
+            }
+        };
+        let next_move  = engine.genmove(&board);
+        {
+            let mut room = room_ptr.lock().unwrap();
+            room.thinking = false;
+            // everyone has left, e.g. in an engine vs engine room. The room is
+            // already removed, so nobody would see the game go on.
+            if room.clients.is_empty() {
+                return;
+            }
+            // the room may be reset or the seat may be changed while searching
+            if room.board != board || room.turn != Some(turn) ||
+               room.seats[turn as usize] != Seat::Engine(config) {
+                drop(room);
+                start_engine(room_ptr);
+                return;
+            }
+            let played = match next_move {
+                Some(next_move) if !engine.has_resigned() =>
+                    room.play(turn, next_move, engine.search_info()),
+                _ => room.resign(turn, engine.search_info()),
+            };
+            if let Err(err) = played {
+                room.stop_engine(turn, &err);
+                return;
+            }
+            room.broadcast_state();
+        }
+        start_engine(room_ptr);
+    });
+}
+
+type Rooms = Arc<Mutex<HashMap<String, Arc<Mutex<Room>>>>>;
+
+pub struct Server {
+    listener: TcpListener,
+    rooms:    Rooms,
+}
+
+impl Server {
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        Ok(Server{
+            listener: TcpListener::bind(addr)?,
+            rooms:    Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    // accept connections forever. Each connection is handled in its own thread.
+    pub fn run(&self) -> io::Result<()> {
+        for (id, stream) in self.listener.incoming().enumerate() {
+            let stream = stream?;
+            let rooms  = Arc::clone(&self.rooms);
+            thread::spawn(move || {
+                if let Err(err) = Connection::accept(id, stream, rooms) {
+                    eprintln!("connection {}: {}", id, err);
+                }
+            });
+        }
+        Ok(())
+    }
+}
+
+struct Connection {
+    id:     usize,
+    rooms:  Rooms,
+    room:   Option<Arc<Mutex<Room>>>,
+    sender: Sender<String>,
+}
+
+impl Connection {
+    fn accept(id: usize, stream: TcpStream, rooms: Rooms) -> Result<(), String> {
+        let mut socket: WebSocket<TcpStream> = tungstenite::accept(stream)
+            .map_err(|err| err.to_string())?;
+        socket.get_ref().set_read_timeout(Some(POLL_INTERVAL))
+            .map_err(|err| err.to_string())?;
+
+        let (sender, receiver) = channel();
+        let mut connection = Connection{id, rooms, room: None, sender};
+        let result = 'connection: loop {
+            match socket.read() {
+                Ok(Message::Text(text)) => {
+                    if let Err(err) = connection.handle(&text) {
+                        connection.sender.send(error_message(&err)).unwrap();
+                    }
+                }
+                Ok(Message::Close(_)) => break Ok(()),
+                Ok(_) => {} // ping/pong is handled by tungstenite
+                Err(tungstenite::Error::Io(ref err))
+                    if err.kind() == io::ErrorKind::WouldBlock ||
+                       err.kind() == io::ErrorKind::TimedOut => {}
+                Err(tungstenite::Error::ConnectionClosed) => break Ok(()),
+                Err(err) => break Err(err.to_string()),
+            }
+            for message in receiver.try_iter() {
+                if let Err(err) = socket.send(Message::text(message)) {
+                    break 'connection Err(err.to_string());
+                }
+            }
+        };
+        connection.leave();
+        result
+    }
+
+    fn handle(&mut self, text: &str) -> Result<(), String> {
+        let request: Request = serde_json::from_str(text).map_err(|err| err.to_string())?;
+        if let Request::Join{room, seat, size} = request {
+            return self.join(room, seat, size);
+        }
+
+        let room_ptr = Arc::clone(self.room.as_ref().ok_or_else(|| "join a room first".to_string())?);
+        {
+            let mut room = room_ptr.lock().unwrap();
+            match request {
+                Request::Join{..} => unreachable!(),
+                Request::Move{next_move} => {
+                    let color = room.seat_of(self.id).ok_or_else(|| "you are not seated".to_string())?;
+                    room.play(color, next_move.parse()?, None)?;
+                }
+                Request::Engine{color, engine} => {
+                    let color: Color = color.parse()?;
+                    if room.seats[color as usize] != Seat::Open {
+                        return Err(format!("{} seat is taken", color));
+                    }
+                    let config: EngineConfig = engine.parse()?;
+                    config.build(color, 0, room.board.width as usize)?;
+                    room.seats[color as usize] = Seat::Engine(config);
+                }
+                Request::Resign => {
+                    let color = room.seat_of(self.id).ok_or_else(|| "you are not seated".to_string())?;
+                    room.resign(color, None)?;
+                }
+                Request::Reset{size} => {
+                    room.seat_of(self.id).ok_or_else(|| "you are not seated".to_string())?;
+                    room.reset(parse_size(size)?);
+                }
+            }
+            room.broadcast_state();
+        }
+        start_engine(room_ptr);
+        Ok(())
+    }
+
+    fn join(&mut self, name: String, seat: Option<String>, size: Option<usize>) -> Result<(), String> {
+        if self.room.is_some() {
+            return Err("already joined".to_string());
+        }
+        let seat = match seat.as_deref() {
+            None | Some("spectator") => None,
+            Some(color)              => Some(color.parse::<Color>()?),
+        };
+
+        let room_ptr = {
+            let mut rooms = self.rooms.lock().unwrap();
+            match rooms.get(&name) {
+                Some(room) => Arc::clone(room),
+                None => {
+                    let seed = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64;
+                    let room = Arc::new(Mutex::new(Room::new(name.clone(), parse_size(size)?, seed)));
+                    rooms.insert(name, Arc::clone(&room));
+                    room
+                }
+            }
+        };
+        {
+            let mut room = room_ptr.lock().unwrap();
+            if let Some(color) = seat {
+                if room.seats[color as usize] != Seat::Open {
+                    return Err(format!("{} seat is taken", color));
+                }
+                room.seats[color as usize] = Seat::Human(self.id);
+            }
+            let history = json!({"type": "history", "plies": room.history});
+            self.sender.send(history.to_string()).unwrap();
+            room.clients.push(Client{id: self.id, sender: self.sender.clone()});
+            room.broadcast_state();
+        }
+        self.room = Some(room_ptr);
+        Ok(())
+    }
+
+    // leave the room and open the seat. An empty room is removed.
+    fn leave(&mut self) {
+        let room_ptr = match self.room.take() {
+            Some(room) => room,
+            None       => return,
+        };
+        let mut rooms = self.rooms.lock().unwrap();
+        let mut room  = room_ptr.lock().unwrap();
+        if let Some(color) = room.seat_of(self.id) {
+            room.seats[color as usize] = Seat::Open;
+        }
+        let id = self.id;
+        room.clients.retain(|client| client.id != id);
+        if room.clients.is_empty() {
+            rooms.remove(&room.name);
+        } else {
+            room.broadcast_state();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn start_server() -> SocketAddr {
+        let server = Server::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+        thread::spawn(move || server.run().unwrap());
+        addr
+    }
+
+    fn connect(addr: SocketAddr) -> WebSocket<TcpStream> {
+        let stream = TcpStream::connect(addr).unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+        tungstenite::client(format!("ws://{}/", addr), stream).unwrap().0
+    }
+
+    fn send(socket: &mut WebSocket<TcpStream>, message: Value) {
+        socket.send(Message::text(message.to_string())).unwrap();
+    }
+
+    fn receive(socket: &mut WebSocket<TcpStream>) -> Value {
+        loop {
+            if let Message::Text(text) = socket.read().unwrap() {
+                return serde_json::from_str(&text).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn two_players_play_over_websocket() {
+        let addr = start_server();
+        let mut red  = connect(addr);
+        let mut blue = connect(addr);
+
+        send(&mut red, json!({"type": "join", "room": "test", "seat": "red", "size": 5}));
+        assert_eq!(receive(&mut red), json!({"type": "history", "plies": []}));
+        let state = receive(&mut red);
+        assert_eq!(state["seats"]["red"], "human");
+        assert_eq!(state["seats"]["blue"], "open");
+        assert_eq!(state["board"], serde_json::from_str::<Value>(&Board::new(5).to_json()).unwrap());
+
+        send(&mut blue, json!({"type": "join", "room": "test", "seat": "red"}));
+        assert_eq!(receive(&mut blue), json!({"type": "error", "message": "red seat is taken"}));
+        send(&mut blue, json!({"type": "join", "room": "test", "seat": "blue"}));
+        assert_eq!(receive(&mut red)["seats"]["blue"], "human");
+        assert_eq!(receive(&mut blue)["type"], "history");
+        assert_eq!(receive(&mut blue)["turn"], "red");
+
+        send(&mut blue, json!({"type": "move", "move": "a5-b4-b3"}));
+        assert_eq!(receive(&mut blue)["message"], "not your turn");
+        send(&mut red, json!({"type": "move", "move": "a1-b2-33"}));
+        assert_eq!(receive(&mut red)["message"], "invalid coordinate: 33");
+        send(&mut red, json!({"type": "move", "move": "a1-b2-c3-d4"}));
+        assert_eq!(receive(&mut red)["message"], "a move needs 3 grids: a1-b2-c3-d4");
+        send(&mut red, json!({"type": "move", "move": "a1-a2-b3"}));
+        assert_eq!(receive(&mut red)["message"], "illegal move");
+
+        send(&mut red, json!({"type": "move", "move": "a1-b2-b3"}));
+        for socket in [&mut red, &mut blue].iter_mut() {
+            assert_eq!(receive(socket), json!({"type": "ply", "ply": 1, "color": "red",
+                "move": "a1-b2-b3", "scores": {"red": 1, "blue": 1}, "search": null}));
+            let state = receive(socket);
+            assert_eq!(state["last_move"], "a1-b2-b3");
+            assert_eq!(state["turn"], "blue");
+        }
+
+        send(&mut blue, json!({"type": "resign"}));
+        assert_eq!(receive(&mut red)["move"], "resign");
+        let state = receive(&mut red);
+        assert_eq!(state["gameover"], true);
+        assert_eq!(state["result"], "red");
+    }
+
+    #[test]
+    fn engine_plays_in_a_room() {
+        let addr = start_server();
+        let mut red = connect(addr);
+
+        send(&mut red, json!({"type": "join", "room": "engine", "seat": "red", "size": 5}));
+        receive(&mut red);
+        receive(&mut red);
+        send(&mut red, json!({"type": "engine", "color": "red", "engine": "greedy"}));
+        assert_eq!(receive(&mut red)["message"], "red seat is taken");
+        send(&mut red, json!({"type": "engine", "color": "blue", "engine": "greedy"}));
+        assert_eq!(receive(&mut red)["seats"]["blue"], "greedy:lookahead=true");
+
+        send(&mut red, json!({"type": "move", "move": "a1-b2-b3"}));
+        assert_eq!(receive(&mut red)["type"], "ply");
+        assert_eq!(receive(&mut red)["turn"], "blue");
+        assert_eq!(receive(&mut red)["color"], "blue");
+        let state = receive(&mut red);
+        assert_eq!(state["turn"], "red");
+
+        let mut board = Board::new(5);
+        board.apply_move("a1-b2-b3".parse().unwrap(), Color::Red);
+        let reply: Move = state["last_move"].as_str().unwrap().parse().unwrap();
+        assert!(board.is_valid_move(reply, Color::Blue));
+    }
+
+    #[test]
+    fn spectators_follow_an_engine_match() {
+        let addr = start_server();
+        let mut spectator = connect(addr);
+
+        send(&mut spectator, json!({"type": "join", "room": "match", "size": 5}));
+        receive(&mut spectator);
+        receive(&mut spectator);
+        send(&mut spectator, json!({"type": "move", "move": "a1-b2-b3"}));
+        assert_eq!(receive(&mut spectator)["message"], "you are not seated");
+
+        send(&mut spectator, json!({"type": "engine", "color": "blue", "engine": "uct:time=0"}));
+        receive(&mut spectator);
+        send(&mut spectator, json!({"type": "engine", "color": "red", "engine": "alphabeta:depth=1"}));
+
+        let mut plies = Vec::new();
+        loop {
+            let message = receive(&mut spectator);
+            if message["type"] == "ply" {
+                let search = &message["search"];
+                if message["color"] == "red" {
+                    assert!(search["depth"].as_u64().unwrap() <= 1);
+                } else {
+                    assert_eq!(search["nodes"], 0);
+                    assert_eq!(search["win_rate"], 0.5);
+                }
+                plies.push(message);
+            } else if message["gameover"] == true {
+                break;
+            }
+        }
+        assert!(!plies.is_empty());
+        assert_eq!(plies[0]["color"], "red");
+
+        // a late joiner receives all the plies at once
+        let mut late = connect(addr);
+        send(&mut late, json!({"type": "join", "room": "match", "seat": "spectator"}));
+        assert_eq!(receive(&mut late), json!({"type": "history", "plies": plies}));
+        assert_eq!(receive(&mut late)["gameover"], true);
+    }
+
+    #[test]
+    fn failed_engine_opens_its_seat() {
+        let path = std::env::temp_dir().join("separo-server-test.bin");
+        std::fs::write(&path, crate::network::Network::random(5, &[8], 0).to_bytes()).unwrap();
+        let config: EngineConfig = format!("puct:weights={},playouts=10", path.to_str().unwrap())
+            .parse().unwrap();
+
+        // the engine cannot play on the board of this room
+        let mut room = Room::new("test".to_string(), 9, 0);
+        let (sender, receiver) = channel();
+        room.clients.push(Client{id: 0, sender});
+        room.seats[Color::Red as usize] = Seat::Engine(config);
+        let room_ptr = Arc::new(Mutex::new(room));
+        start_engine(Arc::clone(&room_ptr));
+
+        let receive = || serde_json::from_str::<Value>(
+            &receiver.recv_timeout(Duration::from_secs(10)).unwrap()).unwrap();
+        assert_eq!(receive(), json!({"type": "error", "message": format!(
+            "red engine stopped: {}: the network is for 5x5 boards, not 9x9", path.to_str().unwrap())}));
+        let state = receive();
+        assert_eq!(state["seats"]["red"], "open");
+        assert_eq!(state["turn"], "red");
+        assert!(!room_ptr.lock().unwrap().thinking);
+        std::fs::remove_file(path).unwrap();
+    }
+}
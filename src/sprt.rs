@@ -0,0 +1,147 @@
+// Sequential probability ratio test (SPRT) between two engines.
+//
+// It tests H0: elo = elo0 against H1: elo = elo1, where elo is the Elo
+// difference of the new engine against the base engine, and stops as soon as
+// the log-likelihood ratio (LLR) crosses one of the bounds decided by alpha
+// (false positive rate) and beta (false negative rate).
+//
+// A game results in a win, a draw (both have the same score, which `playout`
+// reports as None) or a loss. The LLR is computed with the normal
+// approximation of this trinomial model, the same as fishtest, so that draws
+// count as half points and also reduce the variance of the score.
+
+use crate::Color;
+use crate::engine::EngineConfig;
+use crate::game_record::GameRecord;
+use crate::tournament::{Tally, game_seed, play_game};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sprt {
+    pub elo0:  f64,
+    pub elo1:  f64,
+    pub alpha: f64,
+    pub beta:  f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SprtStatus {
+    Continue,
+    AcceptH0, // elo0 is more likely than elo1. e.g. no gain
+    AcceptH1, // elo1 is more likely than elo0. e.g. the new engine is stronger
+}
+
+fn score_from_elo(elo: f64) -> f64 {
+    1.0 / (1.0 + f64::powf(10.0, -elo / 400.0))
+}
+
+impl Default for Sprt {
+    fn default() -> Self {
+        Sprt{elo0: 0.0, elo1: 10.0, alpha: 0.05, beta: 0.05}
+    }
+}
+
+impl Sprt {
+    // (lower, upper). It accepts H0 below the lower bound and H1 above the upper.
+    pub fn bounds(&self) -> (f64, f64) {
+        (f64::ln(self.beta / (1.0 - self.alpha)), f64::ln((1.0 - self.beta) / self.alpha))
+    }
+
+    // While all the games have the same result, the variance is zero and the
+    // LLR is not defined. To avoid it, half a win and half a loss are added to
+    // the games (pseudo-counts), so that e.g. straight wins still count.
+    pub fn llr(&self, tally: &Tally) -> f64 {
+        if tally.games() == 0 {
+            return 0.0;
+        }
+        let n = tally.games() as f64 + 1.0;
+        let w = (tally.wins   as f64 + 0.5) / n;
+        let d =  tally.draws  as f64        / n;
+        let l = (tally.losses as f64 + 0.5) / n;
+        let score = w + 0.5 * d;
+        let variance = w * (1.0 - score).powi(2) + d * (0.5 - score).powi(2) + l * score.powi(2);
+        let s0 = score_from_elo(self.elo0);
+        let s1 = score_from_elo(self.elo1);
+        n * (s1 - s0) * (2.0 * score - s0 - s1) / (2.0 * variance)
+    }
+
+    pub fn status(&self, tally: &Tally) -> SprtStatus {
+        let llr = self.llr(tally);
+        let (lower, upper) = self.bounds();
+        if llr <= lower {
+            SprtStatus::AcceptH0
+        } else if upper <= llr {
+            SprtStatus::AcceptH1
+        } else {
+            SprtStatus::Continue
+        }
+    }
+
+    // play games between the new and the base engine until the test ends or
+    // `max_games` games are played. The colors alternate and the two games
+    // with swapped colors share a seed, as in `Tournament`. `on_game` is
+    // called after each game with the results and the LLR so far.
+    pub fn run<F>(&self, new: &EngineConfig, base: &EngineConfig, size: usize, seed: u64,
+                  max_games: usize, mut on_game: F) -> (SprtStatus, Tally)
+        where F: FnMut(&GameRecord, &Tally, f64) {
+        let mut tally = Tally::default();
+        for game in 0..max_games {
+            let new_color = if game.is_multiple_of(2) {Color::Red} else {Color::Blue};
+            let (red, blue) = if new_color == Color::Red {(new, base)} else {(base, new)};
+            let record = play_game(red, blue, size, game_seed(seed, (game - game % 2) as u64));
+
+            match record.winner().unwrap() {
+                Some(color) if color == new_color => tally.wins   += 1,
+                Some(_)                           => tally.losses += 1,
+                None                              => tally.draws  += 1,
+            }
+            on_game(&record, &tally, self.llr(&tally));
+
+            let status = self.status(&tally);
+            if status != SprtStatus::Continue {
+                return (status, tally);
+            }
+        }
+        (SprtStatus::Continue, tally)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn llr_with_draws() {
+        let sprt = Sprt::default();
+        let (lower, upper) = sprt.bounds();
+        assert!((lower + 2.944).abs() < 1e-3 && (upper - 2.944).abs() < 1e-3);
+
+        let even = Tally{wins: 1000, draws: 0, losses: 1000};
+        assert!(sprt.llr(&even) < 0.0);
+        assert!(0.0 < sprt.llr(&Tally{wins: 60, draws: 0, losses: 40}));
+        assert!(sprt.llr(&Tally{wins: 0, draws: 10, losses: 0}) < 0.0);
+        assert_eq!(sprt.llr(&Tally::default()), 0.0);
+
+        // with the same score, draws reduce the variance and make it decisive
+        let drawish = Tally{wins: 200, draws: 1600, losses: 200};
+        assert!(sprt.llr(&drawish) < sprt.llr(&even));
+        assert_eq!(sprt.status(&drawish), SprtStatus::AcceptH0);
+        assert_eq!(sprt.status(&even), SprtStatus::Continue);
+    }
+
+    #[test]
+    fn straight_results() {
+        let sprt = Sprt::default();
+        assert_eq!(sprt.status(&Tally{wins: 100, draws: 0, losses: 0}), SprtStatus::AcceptH1);
+        assert_eq!(sprt.status(&Tally{wins: 0, draws: 0, losses: 100}), SprtStatus::AcceptH0);
+        assert!(0.0 < sprt.llr(&Tally{wins: 1, draws: 0, losses: 0}));
+    }
+
+    #[test]
+    fn stronger_engine_passes() {
+        let sprt = Sprt{elo0: 0.0, elo1: 200.0, alpha: 0.1, beta: 0.1};
+        let new  = EngineConfig::AlphaBeta{depth: 2, time: 1};
+        let (status, tally) = sprt.run(&new, &EngineConfig::Random, 5, 42, 100, |_, _, _| {});
+        assert_eq!(status, SprtStatus::AcceptH1);
+        assert!(tally.losses < tally.wins);
+    }
+}
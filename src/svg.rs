@@ -0,0 +1,379 @@
+// Vector images of boards, e.g. for blog posts or explanations of the rules.
+//
+// `Board::to_svg` draws a position with optional coordinates, move numbers,
+// the last move and the regions that score. `animate` draws a whole game as
+// an animated SVG; each element is shown and hidden by SMIL `<animate>`, so
+// that it plays in a browser without any script and loops forever.
+//
+// The stones and roots are the same data as `Board::to_json` and the colors
+// are those of `render::Theme`.
+
+use crate::{Board, Color, Move};
+use crate::game_record::GameRecord;
+use crate::render::{Rgba, Theme};
+
+use wasm_bindgen::prelude::*;
+
+use std::fmt::Write;
+
+const GRID:         f64 = 50.0;
+const MARGIN:       f64 = 40.0; // room for the coordinates
+const CAPTION:      f64 = 40.0; // height of the scores below the board in animations
+const STONE_STROKE: f64 =  2.0;
+const ROOT_STROKE:  f64 =  4.0;
+const GRID_STROKE:  f64 =  1.5;
+const HIGHLIGHT:    f64 = 14.0; // width of the band under the last move
+const REGION_ALPHA: f64 =  0.3;
+const HOLD_FRAMES:  usize = 2;  // the final position is shown a bit longer
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SvgOptions {
+    pub coordinates:  bool, // a, b, c, ... and 1, 2, 3, ... around the board
+    pub move_numbers: bool, // the number of the move on each placed stone
+    pub last_move:    bool, // highlight the last move in `history`
+    pub regions:      bool, // shade the regions that score (more than 4 nodes)
+    #[wasm_bindgen(skip)]
+    pub theme:        Theme,
+    #[wasm_bindgen(skip)]
+    pub history:      Vec<Option<Move>>, // the moves so far, None means pass
+}
+
+#[wasm_bindgen]
+impl SvgOptions {
+    pub fn new() -> Self {
+        SvgOptions::default()
+    }
+
+    // appends a move to the history, like `a1-b2-b3` or `pass`. It returns
+    // false if the text is not a move.
+    pub fn add_move(&mut self, text: &str) -> bool {
+        if text.trim() == "pass" {
+            self.history.push(None);
+            return true;
+        }
+        match text.parse::<Move>() {
+            Ok(mv) => {self.history.push(Some(mv)); true}
+            Err(_) => false,
+        }
+    }
+}
+
+// An animated replay of a game record. Each position is shown for
+// `seconds_per_move` seconds. The option `history` is not used.
+#[wasm_bindgen]
+pub fn animated_svg(record: &str, options: &SvgOptions, seconds_per_move: f64)
+    -> Result<String, JsValue> {
+    let record = record.parse::<GameRecord>().map_err(|e| JsValue::from_str(&e))?;
+    animate(&record, options, seconds_per_move).map_err(|e| JsValue::from_str(&e))
+}
+
+fn to_point(x: i8, y: i8) -> (f64, f64) {
+    (MARGIN + x as f64 * GRID, MARGIN + y as f64 * GRID)
+}
+
+// `fill="#rrggbb" fill-opacity="a"`, or with `stroke`
+fn paint(attr: &str, color: Rgba, alpha: f64) -> String {
+    let opacity = color[3] as f64 / 255.0 * alpha;
+    let mut s = format!(r##"{}="#{:02x}{:02x}{:02x}""##, attr, color[0], color[1], color[2]);
+    if opacity < 1.0 {
+        write!(s, r#" {}-opacity="{:.3}""#, attr, opacity).unwrap();
+    }
+    s
+}
+
+// an `<animate>` that shows the parent from the frame `from` until the frame
+// `to` (exclusive; None means until the end). Nothing if always shown.
+fn visibility(from: usize, to: Option<usize>, frames: usize, seconds_per_move: f64) -> String {
+    let (values, key_times) = match (from, to) {
+        (0, None)       => return String::new(),
+        (0, Some(to))   => ("1;0".to_string(),   format!("0;{:.4}", to as f64 / frames as f64)),
+        (from, None)    => ("0;1".to_string(),   format!("0;{:.4}", from as f64 / frames as f64)),
+        (from, Some(to)) => ("0;1;0".to_string(), format!("0;{:.4};{:.4}",
+                            from as f64 / frames as f64, to as f64 / frames as f64)),
+    };
+    format!(r#"<animate attributeName="opacity" values="{}" keyTimes="{}" calcMode="discrete" dur="{:.3}s" repeatCount="indefinite"/>"#,
+            values, key_times, frames as f64 * seconds_per_move)
+}
+
+// the number of the move that placed each stone, indexed like `Board::grids`.
+// The stones at the corners are there from the beginning.
+fn move_numbers(width: usize, history: &[Option<Move>]) -> Vec<Option<usize>> {
+    let mut numbers = vec![None; width * width];
+    let last = width as i8 - 1;
+    for (ply, mv) in history.iter().enumerate() {
+        if let Some(Move(_, stone2, stone3)) = mv {
+            for stone in [stone2, stone3].iter() {
+                let corner = (stone.x == 0 || stone.x == last) && (stone.y == 0 || stone.y == last);
+                let idx = stone.x as usize * width + stone.y as usize;
+                if !corner && numbers[idx].is_none() {
+                    numbers[idx] = Some(ply + 1);
+                }
+            }
+        }
+    }
+    numbers
+}
+
+// The outlines of the regions that score, as the `d` of a `<path>`.
+fn region_path(board: &Board, color: Color) -> String {
+    let mut d = String::new();
+    for region in board.regions(color).iter().filter(|r| r.scores) {
+        for polygon in region.outline().iter() {
+            for (i, (x, y)) in polygon.iter().enumerate() {
+                let (px, py) = (MARGIN + x * GRID, MARGIN + y * GRID);
+                write!(d, "{}{} {}", if i == 0 {"M"} else {"L"}, px, py).unwrap();
+            }
+            d.push('Z');
+        }
+    }
+    d
+}
+
+fn open_svg(out: &mut String, width: usize, caption: bool, theme: &Theme) {
+    let size = 2.0 * MARGIN + (width - 1) as f64 * GRID;
+    let height = if caption {size + CAPTION} else {size};
+    writeln!(out, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{1}" viewBox="0 0 {0} {1}" font-family="sans-serif" text-anchor="middle" dominant-baseline="central">"#,
+             size, height).unwrap();
+    writeln!(out, r#"<rect width="100%" height="100%" {}/>"#, paint("fill", theme.background, 1.0)).unwrap();
+}
+
+fn grid_lines(out: &mut String, width: usize, options: &SvgOptions) {
+    let last = width as i8 - 1;
+    let mut d = String::new();
+    for i in 0..width as i8 {
+        let (x0, y0) = to_point(i, 0);
+        let (x1, y1) = to_point(i, last);
+        write!(d, "M{} {}L{} {}", x0, y0, x1, y1).unwrap();
+        let (x0, y0) = to_point(0, i);
+        let (x1, y1) = to_point(last, i);
+        write!(d, "M{} {}L{} {}", x0, y0, x1, y1).unwrap();
+    }
+    writeln!(out, r#"<path d="{}" {} stroke-width="{}"/>"#,
+             d, paint("stroke", options.theme.grid, 1.0), GRID_STROKE).unwrap();
+
+    if options.coordinates {
+        let text = paint("fill", options.theme.text, 1.0);
+        for i in 0..width as i8 {
+            let (x, y) = to_point(i, i);
+            writeln!(out, r#"<text x="{}" y="{}" font-size="16" {}>{}</text>"#,
+                     x, MARGIN / 2.0, text, (b'a' + i as u8) as char).unwrap();
+            writeln!(out, r#"<text x="{}" y="{}" font-size="16" {}>{}</text>"#,
+                     MARGIN / 2.0, y, text, i + 1).unwrap();
+        }
+    }
+}
+
+fn root_line(out: &mut String, from: (i8, i8), to: (i8, i8), color: Rgba, width: f64, alpha: f64) {
+    let (x1, y1) = to_point(from.0, from.1);
+    let (x2, y2) = to_point(to.0, to.1);
+    writeln!(out, r#"<line x1="{}" y1="{}" x2="{}" y2="{}" {} stroke-width="{}" stroke-linecap="round"/>"#,
+             x1, y1, x2, y2, paint("stroke", color, alpha), width).unwrap();
+}
+
+fn stone_circle(out: &mut String, x: i8, y: i8, color: Color, theme: &Theme) {
+    let (cx, cy) = to_point(x, y);
+    writeln!(out, r#"<circle cx="{}" cy="{}" r="{}" {} {} stroke-width="{}"/>"#,
+             cx, cy, GRID * 0.3, paint("fill", theme.fill[color as usize], 1.0),
+             paint("stroke", theme.stroke[color as usize], 1.0), STONE_STROKE).unwrap();
+}
+
+fn number_text(out: &mut String, x: i8, y: i8, number: usize, theme: &Theme) {
+    let (cx, cy) = to_point(x, y);
+    let size = if number < 100 {GRID * 0.3} else {GRID * 0.22};
+    writeln!(out, r#"<text x="{}" y="{}" font-size="{}" {}>{}</text>"#,
+             cx, cy, size, paint("fill", theme.text, 1.0), number).unwrap();
+}
+
+fn last_move_band(out: &mut String, mv: Move, color: Color, theme: &Theme) {
+    let Move(stone1, stone2, stone3) = mv;
+    for (a, b) in [(stone1, stone2), (stone2, stone3)].iter() {
+        root_line(out, (a.x, a.y), (b.x, b.y), theme.guide[color as usize], HIGHLIGHT, 0.3);
+    }
+}
+
+pub fn board_svg(board: &Board, options: &SvgOptions) -> String {
+    let width = board.width as usize;
+    let theme = &options.theme;
+    let mut out = String::new();
+    open_svg(&mut out, width, false, theme);
+
+    if options.regions {
+        for color in [Color::Red, Color::Blue].iter() {
+            writeln!(out, r#"<path d="{}" {}/>"#, region_path(board, *color),
+                     paint("fill", theme.fill[*color as usize], REGION_ALPHA)).unwrap();
+        }
+    }
+    grid_lines(&mut out, width, options);
+
+    if options.last_move {
+        let last = options.history.iter().enumerate().rev()
+            .find_map(|(ply, mv)| mv.map(|mv| (mv, GameRecord::turn_of(ply))));
+        if let Some((mv, color)) = last {
+            last_move_band(&mut out, mv, color, theme);
+        }
+    }
+
+    let (stones, roots) = board.stones_and_roots();
+    for stone in stones.iter() {
+        stone_circle(&mut out, stone.x, stone.y, color_of(stone.color), theme);
+    }
+    for root in roots.iter().filter(|r| (r.x1, r.y1) < (r.x2, r.y2)) { // once per root
+        root_line(&mut out, (root.x1, root.y1), (root.x2, root.y2),
+                  theme.stroke[root.color as usize], ROOT_STROKE, 1.0);
+    }
+
+    if options.move_numbers {
+        for (idx, number) in move_numbers(width, &options.history).into_iter().enumerate() {
+            if let Some(number) = number {
+                number_text(&mut out, (idx / width) as i8, (idx % width) as i8, number, theme);
+            }
+        }
+    }
+    out.push_str("</svg>\n");
+    out
+}
+
+fn color_of(color: i8) -> Color {
+    if color == Color::Red as i8 {Color::Red} else {Color::Blue}
+}
+
+// An animated replay of a game. The frame n shows the position after n moves
+// and lasts `seconds_per_move` seconds, and the final position lasts a bit
+// longer before the replay starts over. The scores are written below the
+// board. It fails if the record is broken.
+pub fn animate(record: &GameRecord, options: &SvgOptions, seconds_per_move: f64)
+    -> Result<String, String> {
+    let mut boards = record.replay()?;
+    let width  = record.size;
+    let theme  = &options.theme;
+    let last   = boards.len() - 1; // the last frame
+    let frames = boards.len() + HOLD_FRAMES;
+    let show   = |from: usize, to: Option<usize>| visibility(from, to, frames, seconds_per_move);
+
+    let mut out = String::new();
+    open_svg(&mut out, width, true, theme);
+
+    if options.regions {
+        for (frame, board) in boards.iter().enumerate() {
+            let to = if frame == last {None} else {Some(frame + 1)};
+            for color in [Color::Red, Color::Blue].iter() {
+                let d = region_path(board, *color);
+                if !d.is_empty() {
+                    writeln!(out, r#"<path d="{}" {}>{}</path>"#, d,
+                             paint("fill", theme.fill[*color as usize], REGION_ALPHA),
+                             show(frame, to)).unwrap();
+                }
+            }
+        }
+    }
+    grid_lines(&mut out, width, options);
+
+    if options.last_move {
+        for (ply, mv) in record.moves.iter().enumerate() {
+            if let Some(mv) = mv {
+                let to = if ply + 1 == last {None} else {Some(ply + 2)};
+                writeln!(out, "<g>{}", show(ply + 1, to)).unwrap();
+                last_move_band(&mut out, *mv, GameRecord::turn_of(ply), theme);
+                out.push_str("</g>\n");
+            }
+        }
+    }
+
+    // the stones and roots stay once they appear
+    let (stones, _) = boards[0].stones_and_roots();
+    for stone in stones.iter() {
+        stone_circle(&mut out, stone.x, stone.y, color_of(stone.color), theme);
+    }
+    let numbers = move_numbers(width, &record.moves);
+    for (ply, mv) in record.moves.iter().enumerate() {
+        if let Some(mv) = mv {
+            let color = GameRecord::turn_of(ply);
+            let Move(stone1, stone2, stone3) = *mv;
+            writeln!(out, "<g>{}", show(ply + 1, None)).unwrap();
+            for stone in [stone2, stone3].iter() {
+                // the third stone may be on a stone of the same color
+                if boards[ply].grids[stone.x as usize * width + stone.y as usize].color.is_none() {
+                    stone_circle(&mut out, stone.x, stone.y, color, theme);
+                }
+            }
+            for (a, b) in [(stone1, stone2), (stone2, stone3)].iter() {
+                root_line(&mut out, (a.x, a.y), (b.x, b.y), theme.stroke[color as usize],
+                          ROOT_STROKE, 1.0);
+            }
+            if options.move_numbers {
+                for stone in [stone2, stone3].iter() {
+                    if numbers[stone.x as usize * width + stone.y as usize] == Some(ply + 1) {
+                        number_text(&mut out, stone.x, stone.y, ply + 1, theme);
+                    }
+                }
+            }
+            out.push_str("</g>\n");
+        }
+    }
+
+    // the scores of each frame
+    let y = 2.0 * MARGIN + (width - 1) as f64 * GRID + CAPTION / 2.0;
+    for (frame, board) in boards.iter_mut().enumerate() {
+        let to = if frame == last {None} else {Some(frame + 1)};
+        writeln!(out, r#"<text x="50%" y="{}" font-size="20">{}<tspan {}>{}: {}</tspan> | <tspan {}>{}: {}</tspan></text>"#,
+                 y, show(frame, to),
+                 paint("fill", theme.stroke[Color::Red as usize], 1.0),
+                 escape(&record.red), board.score(Color::Red),
+                 paint("fill", theme.stroke[Color::Blue as usize], 1.0),
+                 escape(&record.blue), board.score(Color::Blue)).unwrap();
+    }
+    out.push_str("</svg>\n");
+    Ok(out)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::EngineConfig;
+    use crate::tournament::play_game;
+
+    #[test]
+    fn board_to_svg() {
+        let mut board = Board::new(5);
+        let mut options = SvgOptions::new();
+        for ply in 0..3 {
+            let turn = GameRecord::turn_of(ply);
+            let mv = board.possible_moves(turn)[0];
+            board.apply_move(mv, turn);
+            assert!(options.add_move(&mv.to_string()));
+        }
+        assert!(!options.add_move("a1-b2"));
+
+        let plain = board.to_svg(&options);
+        assert!(plain.starts_with("<svg") && plain.ends_with("</svg>\n"));
+        assert_eq!(plain.matches("<circle").count(), 4 + 2 * 3);
+        assert_eq!(plain.matches("<line").count(), 2 * 3);
+        assert!(!plain.contains("<text"));
+
+        let full = board.to_svg(&SvgOptions{coordinates: true, move_numbers: true,
+                                            last_move: true, regions: true, ..options});
+        // 5 + 5 coordinates and 6 numbers, the last move under the roots
+        assert_eq!(full.matches("<text").count(), 10 + 6);
+        assert_eq!(full.matches(">3</text>").count(), 1 + 2);
+        assert_eq!(full.matches("<line").count(), 2 * 3 + 2);
+        assert_eq!(full.matches("<path").count(), 2 + 1); // regions and the grid
+    }
+
+    #[test]
+    fn animate_game() {
+        let record = play_game(&EngineConfig::Greedy{lookahead: true}, &EngineConfig::Random, 5, 0);
+        let options = SvgOptions{move_numbers: true, ..SvgOptions::default()};
+        let svg = animate(&record, &options, 0.5).unwrap();
+        let moves = record.moves.iter().filter(|m| m.is_some()).count();
+        assert_eq!(svg.matches("<animate ").count(), moves + record.moves.len() + 1);
+        let stones = record.final_board().unwrap().stones_and_roots().0.len();
+        assert_eq!(svg.matches("<circle").count(), stones);
+        let frames = record.moves.len() + 1 + HOLD_FRAMES;
+        assert!(svg.contains(&format!(r#"dur="{:.3}s""#, frames as f64 * 0.5)));
+    }
+}
@@ -0,0 +1,323 @@
+// Matches between engine configurations to measure their strength.
+//
+// In a round robin, every pair of engines plays `games` games. In a gauntlet,
+// the first engine plays `games` games against each of the others. The colors
+// alternate in each pair, and the two games with swapped colors share the
+// same seed, so that the engines face the same randomness from both sides.
+
+use crate::{Board, Color};
+use crate::engine::EngineConfig;
+use crate::game_record::GameRecord;
+
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pairing {
+    RoundRobin,
+    Gauntlet,
+}
+
+// a seed for each game, derived from the tournament seed (splitmix64).
+pub fn game_seed(seed: u64, index: u64) -> u64 {
+    let mut z = seed.wrapping_add(index.wrapping_add(1).wrapping_mul(0x9e3779b97f4a7c15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+// play a game until it ends (see `Board::is_decided`) or someone resigns.
+// It panics if an engine cannot be built, see `EngineConfig::build`.
+pub fn play_game(red: &EngineConfig, blue: &EngineConfig, size: usize, seed: u64) -> GameRecord {
+    let build = |config: &EngineConfig, color, seed| config.build(color, seed, size)
+        .unwrap_or_else(|err| panic!("{}: {}", config, err));
+    let mut engines = [build(red,  Color::Red,  game_seed(seed, 0)),
+                       build(blue, Color::Blue, game_seed(seed, 1))];
+    let mut record = GameRecord::new(size, red.to_string(), blue.to_string());
+    record.tags.push(("Seed".to_string(), seed.to_string()));
+
+    let mut board = Board::new(size);
+    while !board.is_gameover() && !board.is_decided() {
+        let turn   = GameRecord::turn_of(record.moves.len());
+        let engine = &mut engines[turn as usize];
+        let next_move = engine.genmove(&board);
+        if engine.has_resigned() {
+            record.resigned = Some(turn);
+            break;
+        }
+        if let Some(next_move) = next_move {
+            assert!(board.is_valid_move(next_move, turn));
+            board.apply_move(next_move, turn);
+        }
+        record.moves.push(next_move);
+    }
+    record
+}
+
+// An Elo difference estimated from a score rate, with the half width of its
+// 95% confidence interval. The interval is computed from the variance of the
+// score per game, like cutechess-cli does.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Elo {
+    pub diff:  f64,
+    pub error: f64,
+}
+
+pub fn elo_from_score(score: f64) -> f64 {
+    -400.0 * f64::log10(1.0 / score - 1.0)
+}
+
+impl Elo {
+    pub fn new(wins: usize, draws: usize, losses: usize) -> Self {
+        if wins + draws + losses == 0 {
+            return Elo{diff: 0.0, error: f64::INFINITY};
+        }
+        // A score of 0 or 1 means an infinite difference. In that case, half a
+        // game is counted the other way so that the estimate stays finite.
+        let (mut wins, draws, mut losses) = (wins as f64, draws as f64, losses as f64);
+        if draws + losses == 0.0 {
+            wins   -= 0.5;
+            losses += 0.5;
+        } else if wins + draws == 0.0 {
+            wins   += 0.5;
+            losses -= 0.5;
+        }
+        let n = wins + draws + losses;
+        let w = wins   / n;
+        let d = draws  / n;
+        let l = losses / n;
+        let score = w + 0.5 * d;
+        let variance = w * (1.0 - score).powi(2) + d * (0.5 - score).powi(2) + l * score.powi(2);
+        let margin = 1.959964 * f64::sqrt(variance / n);
+
+        // the interval is also kept within half a game from 0 and 1
+        let (min, max) = (0.5 / n, 1.0 - 0.5 / n);
+        let diff  = elo_from_score(score);
+        let upper = elo_from_score(f64::min(score + margin, max));
+        let lower = elo_from_score(f64::max(score - margin, min));
+        let error = (upper - lower) / 2.0;
+        Elo{diff, error}
+    }
+}
+
+impl fmt::Display for Elo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // `+ 0.0` turns -0 into +0
+        write!(f, "{:+.0} +/- {:.0}", self.diff.round() + 0.0, self.error)
+    }
+}
+
+// wins, draws, losses of a row engine against a column engine.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Tally {
+    pub wins:   usize,
+    pub draws:  usize,
+    pub losses: usize,
+}
+
+impl Tally {
+    pub fn games(&self) -> usize {
+        self.wins + self.draws + self.losses
+    }
+    pub fn points(&self) -> f64 {
+        self.wins as f64 + 0.5 * self.draws as f64
+    }
+    pub fn add(&mut self, other: Tally) {
+        self.wins   += other.wins;
+        self.draws  += other.draws;
+        self.losses += other.losses;
+    }
+}
+
+pub struct Tournament {
+    pub engines: Vec<EngineConfig>,
+    pub pairing: Pairing,
+    pub size:    usize,
+    pub games:   usize, // games per pair
+    pub seed:    u64,
+}
+
+pub struct TournamentResult {
+    pub names:   Vec<String>,
+    pub table:   Vec<Vec<Tally>>, // table[i][j]: results of i against j
+    pub records: Vec<GameRecord>,
+}
+
+impl Tournament {
+    pub fn pairs(&self) -> Vec<(usize, usize)> {
+        let n = self.engines.len();
+        match self.pairing {
+            Pairing::RoundRobin => (0..n).flat_map(|i| (i+1..n).map(move |j| (i, j))).collect(),
+            Pairing::Gauntlet   => (1..n).map(|j| (0, j)).collect(),
+        }
+    }
+
+    // play all the games. `on_game` is called after each game, e.g. to show
+    // the progress.
+    pub fn run<F: FnMut(&GameRecord)>(&self, mut on_game: F) -> TournamentResult {
+        let n = self.engines.len();
+        let mut table   = vec![vec![Tally::default(); n]; n];
+        let mut records = Vec::new();
+        let mut index: u64 = 0;
+        for (i, j) in self.pairs() {
+            for game in 0..self.games {
+                // swap colors every game. A pair of swapped games shares a seed.
+                let (red, blue) = if game.is_multiple_of(2) {(i, j)} else {(j, i)};
+                let seed = game_seed(self.seed, index - (game % 2) as u64);
+                index += 1;
+
+                let mut record = play_game(&self.engines[red], &self.engines[blue], self.size, seed);
+                record.tags.insert(0, ("Game".to_string(), index.to_string()));
+                match record.winner().unwrap() {
+                    Some(Color::Red) => {
+                        table[red][blue].wins   += 1;
+                        table[blue][red].losses += 1;
+                    }
+                    Some(Color::Blue) => {
+                        table[blue][red].wins   += 1;
+                        table[red][blue].losses += 1;
+                    }
+                    None => {
+                        table[red][blue].draws += 1;
+                        table[blue][red].draws += 1;
+                    }
+                }
+                on_game(&record);
+                records.push(record);
+            }
+        }
+        let names = self.engines.iter().map(|e| e.to_string()).collect();
+        TournamentResult{names, table, records}
+    }
+}
+
+impl TournamentResult {
+    // total results of each engine against all the others
+    pub fn totals(&self) -> Vec<Tally> {
+        self.table.iter().map(|row| {
+            let mut total = Tally::default();
+            row.iter().for_each(|t| total.add(*t));
+            total
+        }).collect()
+    }
+
+    // the Elo difference of each engine against its opponents on average.
+    // None if the engine has played no games.
+    pub fn elo(&self) -> Vec<Option<Elo>> {
+        self.totals().iter().map(|t| if t.games() == 0 {
+            None
+        } else {
+            Some(Elo::new(t.wins, t.draws, t.losses))
+        }).collect()
+    }
+
+    // the archive of all the games, separated by empty lines
+    pub fn archive(&self) -> String {
+        self.records.iter().map(|r| r.to_string()).collect::<Vec<_>>().join("\n")
+    }
+}
+
+// The crosstable, sorted by points. Each cell is the points of the row engine
+// against the column engine.
+//
+//      # engine                    points  games  elo          1      2
+//      1 uct:time=1,...               3.0      4  +191 +/- 497  .    3.0
+//      2 random                       1.0      4  -191 +/- 497  1.0  .
+impl fmt::Display for TournamentResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let totals = self.totals();
+        let elo    = self.elo();
+        let mut order = (0..self.names.len()).collect::<Vec<_>>();
+        order.sort_by(|a, b| totals[*b].points().partial_cmp(&totals[*a].points()).unwrap());
+
+        let width = self.names.iter().map(|n| n.len()).max().unwrap_or(0).max(6);
+        write!(f, "{:>3} {:width$} {:>7} {:>6} {:>14}", "#", "engine", "points", "games", "elo",
+               width = width)?;
+        for rank in 1..=order.len() {
+            write!(f, " {:>5}", rank)?;
+        }
+        writeln!(f)?;
+
+        for (rank, i) in order.iter().enumerate() {
+            let elo = match elo[*i] {
+                Some(elo) if elo.diff.is_finite() && elo.error.is_finite() => elo.to_string(),
+                Some(elo) => format!("{:+}", elo.diff),
+                None      => "-".to_string(),
+            };
+            write!(f, "{:>3} {:width$} {:>7.1} {:>6} {:>14}", rank + 1, self.names[*i],
+                   totals[*i].points(), totals[*i].games(), elo, width = width)?;
+            for j in order.iter() {
+                let tally = self.table[*i][*j];
+                if i == j || tally.games() == 0 {
+                    write!(f, " {:>5}", ".")?;
+                } else {
+                    write!(f, " {:>5.1}", tally.points())?;
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn elo_estimate() {
+        let even = Elo::new(10, 0, 10);
+        assert!(even.diff.abs() < 1e-9);
+        assert!(0.0 < even.error && even.error < 400.0);
+
+        let stronger = Elo::new(76, 0, 24);
+        assert!((stronger.diff - 200.0).abs() < 1.0);
+        assert!(Elo::new(760, 0, 240).error < stronger.error);
+        assert!(Elo::new(0, 20, 0).diff.abs() < 1e-9);
+
+        // a perfect score is finite
+        let perfect = Elo::new(100, 0, 0);
+        assert!(perfect.diff.is_finite() && perfect.error.is_finite());
+        assert!(stronger.diff < perfect.diff);
+        assert!((Elo::new(0, 0, 100).diff + perfect.diff).abs() < 1e-9);
+        assert_eq!(Elo::new(0, 0, 0).diff, 0.0);
+    }
+
+    #[test]
+    fn round_robin_between_random_and_greedy() {
+        let tournament = Tournament{
+            engines: vec![EngineConfig::Random,
+                          EngineConfig::Greedy{lookahead: false},
+                          EngineConfig::Greedy{lookahead: true}],
+            pairing: Pairing::RoundRobin,
+            size:    5,
+            games:   4,
+            seed:    42,
+        };
+        let mut played = 0;
+        let result = tournament.run(|_| played += 1);
+        assert_eq!(played, 12);
+        assert_eq!(result.records.len(), 12);
+        for (i, total) in result.totals().iter().enumerate() {
+            assert_eq!(total.games(), 8);
+            for j in 0..3 {
+                let (a, b) = (result.table[i][j], result.table[j][i]);
+                assert_eq!((a.wins, a.draws, a.losses), (b.losses, b.draws, b.wins));
+            }
+        }
+
+        // colors alternate and swapped games share the seed
+        assert_eq!(result.records[0].red,  result.records[1].blue);
+        assert_eq!(result.records[0].tag("Seed"), result.records[1].tag("Seed"));
+        assert_ne!(result.records[0].tag("Seed"), result.records[2].tag("Seed"));
+
+        // the games can be reproduced from the archive
+        let records = GameRecord::parse_all(&result.archive()).unwrap();
+        assert_eq!(records, result.records);
+        let replayed = play_game(&EngineConfig::Random, &EngineConfig::Greedy{lookahead: false}, 5,
+                                 records[0].tag("Seed").unwrap().parse().unwrap());
+        assert_eq!(replayed.moves, records[0].moves);
+
+        let gauntlet = Tournament{pairing: Pairing::Gauntlet, ..tournament};
+        assert_eq!(gauntlet.pairs(), vec![(0, 1), (0, 2)]);
+    }
+}
@@ -14,14 +14,22 @@ const BLUE          =   1;
 
 const UCT_exploration_weight_coeff = 2.0;
 const UCT_tree_expansion_threshold = 3;
+const AlphaBeta_search_depth       = 4;
+const Greedy_lookahead             = true;
+const MC_temperature               = 0.0; // e.g. 1.0 to diversify the opening
+const MC_temperature_moves         = 4;
+const MC_resign_threshold          = 0.02;
 
 const board_color   = "rgb(255,255,255)";
 const grid_color    = "rgb(0,0,0)";
 const fill_colors   = ['rgba(255,128,128,0.95)', 'rgba(128,128,255,0.95)'];
 const stroke_colors = ['rgb(255,0,0)', 'rgb(0,0,255)'];
+const region_colors = ['rgba(255,128,128,0.2)', 'rgba(128,128,255,0.2)'];
 
 const guide_checkbox = document.getElementById("guide");
 let draw_guide = guide_checkbox.checked;
+const regions_checkbox = document.getElementById("regions");
+let draw_regions = regions_checkbox.checked;
 let is_running = false;
 
 function sleep(ms) {
@@ -71,10 +79,11 @@ document.getElementById("board-size").addEventListener('input', function(e) {
     update_board_size();
 });
 
-let gif_base64 = ""
+let gif_url = ""
+let gif_recorder = null; // reused for every game
 document.getElementById("download-button").addEventListener('click', function(e) {
     let element = document.createElement("a");
-    element.setAttribute("href", gif_base64);
+    element.setAttribute("href", gif_url);
     element.setAttribute("download", "separo.gif");
     element.style.display = "none";
     document.body.appendChild(element);
@@ -100,6 +109,7 @@ async function run(module) {
     let context = canvas.getContext('2d');
 
     const time_limit = Math.floor(document.getElementById("time-limit").valueAsNumber);
+    const level      = Math.floor(document.getElementById("level").valueAsNumber);
 
     const player_setting_R = document.getElementById("red player" );
     const player_setting_B = document.getElementById("blue player");
@@ -114,6 +124,10 @@ async function run(module) {
         draw_guide = guide_checkbox.checked;
         drawBoard(context, separo, player_R, player_B, draw_guide ? "guide turned on" : "guide turned off");
     });
+    regions_checkbox.addEventListener('input', function(e) {
+        draw_regions = regions_checkbox.checked;
+        drawBoard(context, separo, player_R, player_B, draw_regions ? "regions turned on" : "regions turned off");
+    });
 
     if(player_R == "NotSelected" || player_B == "NotSelected") {
         is_running = false;
@@ -309,43 +323,83 @@ async function run(module) {
 
     if(player_R == "Random") {
         playerR = module.RandomPlayer.new(RED, gen_seed(), gen_seed());
+    } else if (player_R == "Greedy") {
+        playerR = module.GreedyPlayer.new(RED, gen_seed(), gen_seed(), Greedy_lookahead);
     } else if (player_R == "Naive MC") {
         playerR = module.NaiveMonteCarlo.new(RED, gen_seed(), gen_seed(), time_limit);
+        playerR.set_temperature(MC_temperature, MC_temperature_moves);
+        playerR.set_resign_threshold(MC_resign_threshold);
     } else if (player_R == "UCT MC") {
         playerR = module.UCTMonteCarlo.new(RED, gen_seed(), gen_seed(), time_limit,
             UCT_exploration_weight_coeff, UCT_tree_expansion_threshold, board_size);
+        playerR.set_temperature(MC_temperature, MC_temperature_moves);
+        playerR.set_resign_threshold(MC_resign_threshold);
+    } else if (player_R == "AlphaBeta") {
+        playerR = module.AlphaBetaPlayer.new(RED, AlphaBeta_search_depth, time_limit);
+    } else if (player_R == "Leveled") {
+        playerR = module.LeveledPlayer.new(RED, gen_seed(), gen_seed(), level, time_limit, board_size);
     } else {
         playerR = {play: human_player(RED)};
     }
     if(player_B == "Random") {
         playerB = module.RandomPlayer.new(BLUE, gen_seed(), gen_seed());
+    } else if (player_B == "Greedy") {
+        playerB = module.GreedyPlayer.new(BLUE, gen_seed(), gen_seed(), Greedy_lookahead);
     } else if (player_B == "Naive MC") {
         playerB = module.NaiveMonteCarlo.new(BLUE, gen_seed(), gen_seed(), time_limit);
+        playerB.set_temperature(MC_temperature, MC_temperature_moves);
+        playerB.set_resign_threshold(MC_resign_threshold);
     } else if (player_B == "UCT MC") {
         playerB = module.UCTMonteCarlo.new(BLUE, gen_seed(), gen_seed(), time_limit,
             UCT_exploration_weight_coeff, UCT_tree_expansion_threshold, board_size);
+        playerB.set_temperature(MC_temperature, MC_temperature_moves);
+        playerB.set_resign_threshold(MC_resign_threshold);
+    } else if (player_B == "AlphaBeta") {
+        playerB = module.AlphaBetaPlayer.new(BLUE, AlphaBeta_search_depth, time_limit);
+    } else if (player_B == "Leveled") {
+        playerB = module.LeveledPlayer.new(BLUE, gen_seed(), gen_seed(), level, time_limit, board_size);
     } else {
         playerB = {play: human_player(BLUE)};
     }
 
-    let gif_recorder = module.GameGifRecorder.new();
-    gif_recorder.add_frame(canvas.toDataURL('image/png'));
-    while(!separo.is_gameover()) {
+    if (gif_recorder === null) {
+        let gif_options = module.GifOptions.new();
+        gif_options.final_delay = 300; // hold the result for 3 seconds
+        gif_recorder = module.GameGifRecorder.with_options(gif_options);
+    } else {
+        gif_recorder.reset(); // discard a game that was not finished
+    }
+    gif_recorder.add_board(separo, player_R, player_B, "Red's turn", draw_guide);
+    const has_resigned = function(player) {
+        return player.has_resigned !== undefined && player.has_resigned();
+    };
+
+    // stop if no move can change the result any more
+    let resigned = null;
+    while(!separo.is_gameover() && !separo.is_decided()) {
         if(separo.can_move(RED)) {
             turn_color = "Red";
             separo = await playerR.play(separo);
+            if(has_resigned(playerR)) {
+                resigned = RED;
+                break;
+            }
 
             drawBoard(context, separo, player_R, player_B, "Blue's turn");
-            gif_recorder.add_frame(canvas.toDataURL('image/png'));
+            gif_recorder.add_board(separo, player_R, player_B, "Blue's turn", draw_guide);
         }
         await sleep(100);
         // -------------------------------------------------------------------
         if(separo.can_move(BLUE)) {
             turn_color = "Blue";
             separo = await playerB.play(separo);
+            if(has_resigned(playerB)) {
+                resigned = BLUE;
+                break;
+            }
 
             drawBoard(context, separo, player_R, player_B, "Red's turn");
-            gif_recorder.add_frame(canvas.toDataURL('image/png'));
+            gif_recorder.add_board(separo, player_R, player_B, "Red's turn", draw_guide);
         }
         await sleep(100);
     }
@@ -353,20 +407,171 @@ async function run(module) {
     let last_score_red  = separo.score(RED);
     let last_score_blue = separo.score(BLUE);
     let result = "draw!";
-    if (last_score_blue < last_score_red) {
+    if (resigned == RED) {
+        result = "Red resigned. Blue wins!";
+    } else if (resigned == BLUE) {
+        result = "Blue resigned. Red wins!";
+    } else if (last_score_blue < last_score_red) {
         result = "Red wins!";
     } else if (last_score_red < last_score_blue) {
         result = "Blue wins!";
     }
     drawBoard(context, separo, player_R, player_B, result);
 
-    gif_recorder.add_frame(canvas.toDataURL('image/png'));
-    gif_base64 = "data:image/gif;base64," + gif_recorder.dump();
+    gif_recorder.add_board(separo, player_R, player_B, result, draw_guide);
+    const gif_bytes = gif_recorder.finish();
+    if (gif_url !== "") {
+        URL.revokeObjectURL(gif_url);
+    }
+    gif_url = URL.createObjectURL(new Blob([gif_bytes], {type: gif_recorder.mime_type()}));
 
     is_running = false;
     return;
 }
 
+// ---------------------------------------------------------------------------
+// Room mode. The page joins a room of separo-server over WebSocket, sends the
+// moves and draws the state that the server pushes (see src/server.rs).
+
+let room_socket = null;
+
+function parse_move(text) {
+    return text.split("-").map(function(grid) {
+        return {x: grid.charCodeAt(0) - "a".charCodeAt(0), y: parseInt(grid.slice(1)) - 1};
+    });
+}
+
+function format_move(stones) {
+    return stones.map(function(stone) {
+        return String.fromCharCode("a".charCodeAt(0) + stone.x) + (stone.y + 1);
+    }).join("-");
+}
+
+function join_room(module) {
+    if(room_socket !== null) {
+        room_socket.close(); // leave the room
+        return;
+    }
+    if(is_running) {return;}
+    is_running = true;
+
+    update_board_size();
+
+    let canvas = document.getElementById("separo-board");
+    scale = canvas_width / canvas.offsetWidth;
+    canvas.width  = canvas_width;
+    canvas.height = canvas_height;
+
+    let context = canvas.getContext('2d');
+
+    const button = document.getElementById("room-button");
+    const seat   = document.getElementById("room-seat").value;
+    const engine = document.getElementById("room-engine").value.trim();
+
+    // The server sends the board as JSON, but the guide and the regions are
+    // computed by the wasm Board. So the plies are replayed on a local board.
+    let board   = module.Board.new(board_size);
+    let plies   = [];
+    let state   = null;
+    let message = "connecting...";
+    let clicked = []; // the grids of the move being chosen
+
+    const redraw = function() {
+        const red_name  = state === null ? "Red"  : state["seats"]["red"];
+        const blue_name = state === null ? "Blue" : state["seats"]["blue"];
+        drawBoard(context, board, red_name, blue_name, message);
+        clicked.forEach(function(xy) {
+            drawTemporaryStone(context, xy_to_pixel(xy));
+        });
+    };
+
+    const apply_ply = function(ply) {
+        if(ply["move"] == "resign") {
+            return;
+        }
+        const s = parse_move(ply["move"]);
+        board.apply_move_if_possible(s[0].x, s[0].y, s[1].x, s[1].y, s[2].x, s[2].y,
+                                     ply["color"] == "red" ? RED : BLUE);
+    };
+
+    const is_synced = function() {
+        return JSON.parse(board.to_json())["roots"].length == state["board"]["roots"].length;
+    };
+
+    const status_message = function() {
+        if(state["gameover"]) {
+            return state["result"] == "draw" ? "draw!" : state["result"] + " wins!";
+        }
+        return state["turn"] + "'s turn" + (state["turn"] == seat ? " (click 3 stones)" : "");
+    };
+
+    const on_click = function(e) {
+        if(state === null || state["turn"] != seat) {
+            return;
+        }
+        clicked.push(mouseevent_to_xy(e));
+        if(clicked.length == 3) {
+            room_socket.send(JSON.stringify({type: "move", move: format_move(clicked)}));
+            clicked = [];
+        }
+        redraw();
+    };
+
+    room_socket = new WebSocket(document.getElementById("room-server").value);
+    button.textContent = "Leave";
+    canvas.addEventListener('click', on_click);
+    guide_checkbox.addEventListener('input', redraw);
+    regions_checkbox.addEventListener('input', redraw);
+    redraw();
+
+    room_socket.onopen = function(e) {
+        room_socket.send(JSON.stringify({type: "join", room: document.getElementById("room-name").value,
+                                         seat: seat, size: board_size}));
+        if(engine !== "" && seat != "spectator") {
+            const opponent = seat == "red" ? "blue" : "red";
+            room_socket.send(JSON.stringify({type: "engine", color: opponent, engine: engine}));
+        }
+    };
+
+    room_socket.onmessage = function(e) {
+        const data = JSON.parse(e.data);
+        if(data["type"] == "history") {
+            plies = data["plies"];
+        } else if(data["type"] == "ply") {
+            plies.push(data);
+            apply_ply(data);
+        } else if(data["type"] == "state") {
+            state = data;
+            if(state["size"] != board_size || !is_synced()) {
+                document.getElementById("board-size").value = state["size"];
+                update_board_size();
+                board = module.Board.new(board_size);
+                plies.forEach(apply_ply);
+                if(!is_synced()) { // the room was reset
+                    plies = [];
+                    board = module.Board.new(board_size);
+                }
+            }
+            message = status_message();
+        } else if(data["type"] == "error") {
+            clicked = [];
+            message = data["message"];
+        }
+        redraw();
+    };
+
+    room_socket.onclose = function(e) {
+        canvas.removeEventListener('click', on_click);
+        guide_checkbox.removeEventListener('input', redraw);
+        regions_checkbox.removeEventListener('input', redraw);
+        room_socket = null;
+        button.textContent = "Join";
+        message = "disconnected";
+        redraw();
+        is_running = false;
+    };
+}
+
 function drawBoard(context, board, red_name, blue_name, msg) {
 
     const board_state = JSON.parse(board.to_json());
@@ -399,6 +604,15 @@ function drawBoard(context, board, red_name, blue_name, msg) {
     context.fillText("|", canvas_width / 2, 40.0);
     context.fillText(msg, canvas_width / 2, 70.0);
 
+    // draw the regions that score under the grid
+    if(draw_regions) {
+        [RED, BLUE].forEach(function(color) {
+            JSON.parse(board.region_outlines_as_json(color)).forEach(function(region) {
+                drawRegion(context, region["polygons"], color);
+            });
+        });
+    }
+
     // draw grid
     context.strokeStyle=grid_color;
     context.lineWidth=2.0
@@ -436,6 +650,29 @@ function drawBoard(context, board, red_name, blue_name, msg) {
     }
 }
 
+// polygons of a region in board coordinates. holes go the other way round,
+// so the default "nonzero" rule leaves them empty.
+function drawRegion(context, polygons, color_idx) {
+    context.beginPath();
+    polygons.forEach(function(polygon) {
+        polygon.forEach(function(point, i) {
+            const px = point[0] * grid_width + board_margin;
+            const py = point[1] * grid_width + board_margin + canvas_header;
+            if (i == 0) {
+                context.moveTo(px, py);
+            } else {
+                context.lineTo(px, py);
+            }
+        });
+        context.closePath();
+    });
+    context.fillStyle   = region_colors[color_idx];
+    context.strokeStyle = stroke_colors[color_idx];
+    context.lineWidth   = 1.0;
+    context.fill();
+    context.stroke();
+}
+
 function drawStone(context, x, y, color_idx) {
     context.beginPath();
 